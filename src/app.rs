@@ -1,12 +1,24 @@
 use eframe::egui::{self, Context};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::assets::load_texture;
 use crate::rom_preview::RomPreview;
-use crate::ui::{draw_background, draw_header};
+use crate::ui::{draw_background, draw_header, draw_message};
+
+/// How long a message stays on screen.
+static MESSAGE_TIME: Duration = Duration::from_secs(6);
 
 pub struct BarelyGameConsole {
     rom_preview: RomPreview,
+    /// A message for the player and when it stops being shown.
+    message: Option<(String, Instant)>,
+    /// Whether the preview shows a failed launch, to be cleared with the message.
+    showing_failure: bool,
+    /// When to close the window to hand the screen over to a game.
+    close_at: Option<Instant>,
+    /// Whether the window was told to close, after which there's no going back.
+    close_sent: bool,
     ctx: Arc<Context>,
 }
 
@@ -14,19 +26,75 @@ impl BarelyGameConsole {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         Self {
             rom_preview: RomPreview::new(),
+            message: None,
+            showing_failure: false,
+            close_at: None,
+            close_sent: false,
             ctx: Arc::new(cc.egui_ctx.clone()),
         }
     }
 
     pub fn enqueue_rom(&mut self, rom: Option<String>) {
-        self.rom_preview.enqueue(rom);
+        self.show_card(rom, None);
+    }
+
+    /// Show a card (or clear the preview), with a countdown if it will be
+    /// launched or cleared after a while.
+    pub fn show_card(&mut self, rom: Option<String>, countdown: Option<Duration>) {
+        self.showing_failure = false;
+        self.rom_preview.enqueue(rom, false, countdown);
+        self.ctx.request_repaint();
+    }
+
+    pub fn show_message(&mut self, message: String) {
+        self.message = Some((message, Instant::now() + MESSAGE_TIME));
+        self.ctx.request_repaint();
+    }
+
+    /// Show a card that failed to launch as an error card, with the reason below.
+    pub fn show_failure(&mut self, artwork: String, reason: String) {
+        self.rom_preview.enqueue(Some(artwork), true, None);
+        self.showing_failure = true;
+        self.show_message(reason);
+    }
+
+    /// Close the window once `delay` is up, giving a just-spawned game time to
+    /// create its surface first.
+    pub fn close_after(&mut self, delay: Duration) {
+        self.close_at = Some(Instant::now() + delay);
         self.ctx.request_repaint();
     }
+
+    /// Stay open after all, unless the window was already told to close.
+    /// Returns whether it stays open.
+    pub fn cancel_close(&mut self) -> bool {
+        if self.close_sent {
+            return false;
+        }
+        self.close_at = None;
+        self.ctx.request_repaint();
+        true
+    }
+
+    /// Whether the window is closing (or closed) for a game.
+    pub fn is_closing(&self) -> bool {
+        self.close_at.is_some()
+    }
 }
 
 impl BarelyGameConsole {
     pub fn update(&mut self, ctx: &egui::Context) {
-        if let Some(texture) = load_texture(&ctx, "assets/background.png") {
+        if let Some(close_at) = self.close_at {
+            if Instant::now() >= close_at && !self.close_sent {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                self.close_sent = true;
+            }
+            // Keep ticking until the deadline
+            ctx.request_repaint();
+            return;
+        }
+
+        if let Some(texture) = load_texture(ctx, "assets/background.png") {
             draw_background(ctx, &texture);
         }
 
@@ -40,5 +108,19 @@ impl BarelyGameConsole {
                     self.rom_preview.update(ctx, ui);
                 });
             });
+
+        if let Some((message, until)) = &self.message {
+            let now = Instant::now();
+            if now < *until {
+                draw_message(ctx, message);
+                ctx.request_repaint_after(*until - now);
+            } else {
+                self.message = None;
+                if self.showing_failure {
+                    self.showing_failure = false;
+                    self.rom_preview.enqueue(None, false, None);
+                }
+            }
+        }
     }
 }
@@ -1,23 +1,1162 @@
-use serde::Deserialize;
+use crate::devices::Devices;
+use crate::limits::Limits;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::Spanned;
 
-#[derive(Deserialize, Debug, Clone)]
+/// Used when neither `--config` nor `BGC_CONFIG` is given.
+pub static DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Clone, Default)]
 pub struct Config {
-    pub rfid_cards: std::collections::HashMap<String, CardInfo>,
+    pub launcher: LauncherSettings,
+    pub limits: Limits,
+    pub devices: Devices,
+    pub rfid_cards: HashMap<String, CardInfo>,
+    /// Every file the config was assembled from: the main file, then fragments.
+    files: Vec<PathBuf>,
+    /// Index into `files` and line of each card's table, for error reporting.
+    card_locations: HashMap<String, (usize, usize)>,
 }
 
+/// Launcher-wide behavior, from the `[launcher]` table of the main config.
 #[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct LauncherSettings {
+    /// How long a game gets to exit after being asked to quit before it's killed.
+    #[serde(deserialize_with = "seconds")]
+    pub grace_period: Duration,
+    /// Where each launch's stdout/stderr is captured.
+    pub log_dir: PathBuf,
+    /// How many launch logs to keep before the oldest are deleted.
+    pub log_keep: usize,
+    /// Append-only JSONL record of every launch.
+    pub history_path: PathBuf,
+    /// Repeated reads of the same card within this long count as one tap.
+    #[serde(deserialize_with = "seconds")]
+    pub debounce: Duration,
+    /// Stop a game when its card is taken off a reader that reports removal.
+    pub stop_on_removal: bool,
+    /// What tapping a card does.
+    pub launch_mode: LaunchMode,
+    /// How long a `delayed` launch waits for another card to be tapped.
+    #[serde(deserialize_with = "seconds")]
+    pub launch_delay: Duration,
+    /// How long a tapped card stays selected in `confirm` mode. Zero keeps it
+    /// until another card is tapped.
+    #[serde(deserialize_with = "seconds")]
+    pub preview_timeout: Duration,
+}
+
+impl Default for LauncherSettings {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::from_secs(5),
+            log_dir: PathBuf::from("logs"),
+            log_keep: 100,
+            history_path: PathBuf::from("history.jsonl"),
+            debounce: Duration::from_secs(2),
+            stop_on_removal: false,
+            launch_mode: LaunchMode::Confirm,
+            launch_delay: Duration::from_secs(3),
+            preview_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// What tapping a card does.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchMode {
+    /// Show the card; the power button launches it.
+    Confirm,
+    /// Launch right away.
+    Instant,
+    /// Show the card and launch it after `launch_delay`, unless another card
+    /// is tapped first.
+    Delayed,
+}
+
+#[derive(Debug, Clone)]
 pub struct CardInfo {
-    pub rom_path: Option<String>,
-    pub emulator: Option<String>,
-    pub command: Option<Vec<String>>,
+    /// The card's key in `rfid_cards`.
+    pub id: String,
+    pub launch: LaunchTarget,
     pub working_dir: Option<String>,
+    /// Extra environment variables for the launched program.
+    pub env: BTreeMap<String, String>,
+    /// Start from an empty environment instead of inheriting the launcher's.
+    pub env_clear: bool,
+    /// Variables kept from the launcher's environment when `env_clear` is set.
+    pub env_inherit: Vec<String>,
     pub artwork: String,
+    /// Overrides `stop_on_removal` from `[launcher]`.
+    pub stop_on_removal: Option<bool>,
+    /// Overrides `launch_mode` from `[launcher]`.
+    pub launch_mode: Option<LaunchMode>,
+    /// Overrides `launch_delay` from `[launcher]`.
+    pub launch_delay: Option<Duration>,
+    /// Overrides `preview_timeout` from `[launcher]`.
+    pub preview_timeout: Option<Duration>,
+}
+
+/// What a card launches. Each kind carries exactly the fields it needs, so a
+/// loaded card is always launchable as far as the schema is concerned.
+#[derive(Debug, Clone)]
+pub enum LaunchTarget {
+    /// A ROM run through a libretro core in RetroArch.
+    RetroArch {
+        core: String,
+        content: Content,
+        /// Extra config files layered over the global retroarch.cfg.
+        append_config: Vec<String>,
+        args: Vec<String>,
+        /// Inline settings, written to a generated config file at launch.
+        settings: BTreeMap<String, String>,
+        /// Save state when quitting and load it again on the next launch.
+        resume: bool,
+    },
+    /// An arbitrary program and its arguments.
+    Command { command: Vec<String> },
+}
+
+/// What RetroArch loads for a card.
+#[derive(Debug, Clone)]
+pub enum Content {
+    /// A single ROM or disc image, or an existing `.m3u` playlist.
+    Rom(String),
+    /// Discs of a multi-disc game, gathered into a generated `.m3u` at launch.
+    Discs(Vec<String>),
+}
+
+/// A named emulator profile (`[systems.snes]`) shared by every card that
+/// references it with `system = "snes"`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct System {
+    core: String,
+    #[serde(default)]
+    append_config: Vec<String>,
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum LaunchKind {
+    RetroArch,
+    Command,
+}
+
+/// A card as written in config.toml. `launch` names the kind explicitly; when
+/// omitted it's inferred from which fields are present.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCardInfo {
+    launch: Option<LaunchKind>,
+    system: Option<String>,
+    rom_path: Option<String>,
+    discs: Option<Vec<String>>,
+    emulator: Option<String>,
+    command: Option<Vec<String>>,
+    #[serde(default)]
+    append_config: Vec<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    settings: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    resume: bool,
+    working_dir: Option<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    env_clear: bool,
+    #[serde(default)]
+    env_inherit: Vec<String>,
+    artwork: String,
+    stop_on_removal: Option<bool>,
+    launch_mode: Option<LaunchMode>,
+    launch_delay: Option<f64>,
+    preview_timeout: Option<f64>,
+}
+
+impl RawCardInfo {
+    /// Turn the raw fields into a launch target, filling RetroArch settings in
+    /// from the referenced system. A card's own `emulator` overrides the system
+    /// core, and its append-configs and arguments come after the system's.
+    fn resolve(self, id: &str, systems: &HashMap<String, System>) -> Result<CardInfo, String> {
+        let kind = match self.launch {
+            Some(kind) => kind,
+            None if self.command.is_some() => LaunchKind::Command,
+            None if self.rom_path.is_some()
+                || self.discs.is_some()
+                || self.emulator.is_some()
+                || self.system.is_some() =>
+            {
+                LaunchKind::RetroArch
+            }
+            None => return Err("must set either command or rom_path + emulator".to_string()),
+        };
+
+        let launch = match kind {
+            LaunchKind::RetroArch => {
+                if self.command.is_some() {
+                    return Err("command cannot be combined with rom_path or emulator".to_string());
+                }
+                let system = match &self.system {
+                    Some(name) => Some(
+                        systems
+                            .get(name)
+                            .ok_or_else(|| format!("unknown system {}", name))?,
+                    ),
+                    None => None,
+                };
+                let core = self
+                    .emulator
+                    .or_else(|| system.map(|s| s.core.clone()))
+                    .ok_or("emulator or system is required for a retroarch launch")?;
+                let settings = self
+                    .settings
+                    .into_iter()
+                    .map(|(key, value)| Ok((key.clone(), setting_value(&key, value)?)))
+                    .collect::<Result<_, String>>()?;
+                let content = match (self.rom_path, self.discs) {
+                    (Some(rom_path), None) => Content::Rom(rom_path),
+                    (None, Some(discs)) if discs.is_empty() => {
+                        return Err("discs must not be empty".to_string())
+                    }
+                    (None, Some(discs)) => Content::Discs(discs),
+                    (Some(_), Some(_)) => {
+                        return Err("rom_path cannot be combined with discs".to_string())
+                    }
+                    (None, None) => {
+                        return Err(
+                            "rom_path or discs is required for a retroarch launch".to_string()
+                        )
+                    }
+                };
+                LaunchTarget::RetroArch {
+                    core,
+                    content,
+                    append_config: system
+                        .map(|s| s.append_config.clone())
+                        .unwrap_or_default()
+                        .into_iter()
+                        .chain(self.append_config)
+                        .collect(),
+                    args: system
+                        .map(|s| s.args.clone())
+                        .unwrap_or_default()
+                        .into_iter()
+                        .chain(self.args)
+                        .collect(),
+                    settings,
+                    resume: self.resume,
+                }
+            }
+            LaunchKind::Command => {
+                if self.rom_path.is_some()
+                    || self.discs.is_some()
+                    || self.emulator.is_some()
+                    || self.system.is_some()
+                {
+                    return Err(
+                        "command cannot be combined with rom_path, discs, emulator or system"
+                            .to_string(),
+                    );
+                }
+                if !self.append_config.is_empty()
+                    || !self.args.is_empty()
+                    || !self.settings.is_empty()
+                    || self.resume
+                {
+                    return Err(
+                        "append_config, args, settings and resume only apply to retroarch launches"
+                            .to_string(),
+                    );
+                }
+                let command = self
+                    .command
+                    .ok_or("command is required for a command launch")?;
+                if command.is_empty() {
+                    return Err("command must not be empty".to_string());
+                }
+                LaunchTarget::Command { command }
+            }
+        };
+
+        if !self.env_inherit.is_empty() && !self.env_clear {
+            return Err("env_inherit only applies when env_clear is set".to_string());
+        }
+        let launch_delay = optional_seconds("launch_delay", self.launch_delay)?;
+        let preview_timeout = optional_seconds("preview_timeout", self.preview_timeout)?;
+
+        Ok(CardInfo {
+            id: id.to_string(),
+            launch,
+            working_dir: self.working_dir,
+            env: self.env,
+            env_clear: self.env_clear,
+            env_inherit: self.env_inherit,
+            artwork: self.artwork,
+            stop_on_removal: self.stop_on_removal,
+            launch_mode: self.launch_mode,
+            launch_delay,
+            preview_timeout,
+        })
+    }
+}
+
+/// Mirrors the on-disk layout, keeping each card and system as a spanned raw
+/// value so one malformed entry doesn't hide problems in the others.
+#[derive(Deserialize)]
+struct RawConfig {
+    launcher: Option<Spanned<toml::Value>>,
+    limits: Option<Spanned<toml::Value>>,
+    devices: Option<Spanned<toml::Value>>,
+    #[serde(default)]
+    systems: HashMap<String, Spanned<toml::Value>>,
+    #[serde(default)]
+    rfid_cards: HashMap<String, Spanned<toml::Value>>,
+    /// Top-level keys that aren't any of the above, e.g. a misspelled table.
+    #[serde(skip)]
+    unknown: Vec<Spanned<String>>,
+}
+
+/// Everything that may appear at the top level of a config file.
+static TOP_LEVEL_KEYS: &[&str] = &["launcher", "limits", "devices", "systems", "rfid_cards"];
+
+/// A single problem found while loading or checking the config.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    /// What the problem is about, e.g. `card 0005593265` or `system snes`.
+    pub subject: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(subject) = &self.subject {
+            write!(f, ": {}", subject)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(Vec<Problem>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "failed to read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "failed to parse {}: {}", path.display(), e),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid config:")?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl Config {
-    pub fn load() -> Self {
-        let config = fs::read_to_string("config.toml").expect("Failed to read config.toml");
-        toml::from_str(&config).expect("Failed to parse config.toml")
+    /// Load the config at `path`, merged with any `*.toml` fragments in the
+    /// `config.d/` directory beside it. Fails if any card doesn't deserialize into
+    /// a launch target or is defined twice; see [`Config::check`] for checks
+    /// against the filesystem.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let (config, mut problems) = Self::assemble(path, false)?;
+        if !problems.is_empty() {
+            config.sort_problems(&mut problems);
+            return Err(ConfigError::Invalid(problems));
+        }
+        Ok(config)
+    }
+
+    /// Order problems the way the config reads: by file, then by line.
+    pub fn sort_problems(&self, problems: &mut [Problem]) {
+        problems.sort_by_key(|p| {
+            let index = self.files.iter().position(|f| *f == p.file);
+            (index, p.line)
+        });
+    }
+
+    /// Like [`Config::load`], but leaves out whatever is broken instead of
+    /// failing: fragments that can't be read or parsed, and invalid cards,
+    /// systems or tables, are skipped and returned as problems. Only a main
+    /// config that can't be read or parsed is an error.
+    pub fn load_partial(path: &Path) -> Result<(Self, Vec<Problem>), ConfigError> {
+        Self::assemble(path, true)
+    }
+
+    fn assemble(path: &Path, skip_broken: bool) -> Result<(Self, Vec<Problem>), ConfigError> {
+        let mut files = vec![path.to_path_buf()];
+        let mut problems = Vec::new();
+        let mut sources = Vec::new();
+        for file in fragment_paths(path)? {
+            match read_source(&file) {
+                Ok(read) => {
+                    files.push(file);
+                    sources.push(read);
+                }
+                Err(ConfigError::Read(file, e)) if skip_broken => problems.push(Problem {
+                    file,
+                    line: None,
+                    subject: None,
+                    message: format!("failed to read: {}", e),
+                }),
+                Err(ConfigError::Parse(file, e)) if skip_broken => {
+                    let line = fs::read_to_string(&file)
+                        .ok()
+                        .zip(e.span())
+                        .map(|(source, span)| line_of(&source, span.start));
+                    problems.push(Problem {
+                        file,
+                        line,
+                        subject: None,
+                        message: format!("failed to parse: {}", e.message()),
+                    });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        sources.insert(0, read_source(path)?);
+
+        for (index, (source, raw)) in sources.iter_mut().enumerate() {
+            for key in raw.unknown.drain(..) {
+                problems.push(Problem {
+                    file: files[index].clone(),
+                    line: Some(line_of(source, key.span().start)),
+                    subject: Some(key.into_inner()),
+                    message: format!(
+                        "unknown top-level key, expected one of {}",
+                        TOP_LEVEL_KEYS.join(", ")
+                    ),
+                });
+            }
+        }
+
+        let launcher = main_table(&files, &mut sources, &mut problems, "launcher", |raw| {
+            raw.launcher.take()
+        });
+        let limits = main_table(&files, &mut sources, &mut problems, "limits", |raw| {
+            raw.limits.take()
+        });
+        let devices = main_table(&files, &mut sources, &mut problems, "devices", |raw| {
+            raw.devices.take()
+        });
+
+        // Systems first, so cards in any file can reference systems from any other
+        let mut systems = HashMap::new();
+        let mut system_locations: HashMap<String, (usize, usize)> = HashMap::new();
+        for (index, (source, raw)) in sources.iter_mut().enumerate() {
+            for (name, value) in raw.systems.drain() {
+                let line = line_of(source, value.span().start);
+                let problem = |message: String| Problem {
+                    file: files[index].clone(),
+                    line: Some(line),
+                    subject: Some(format!("system {}", name)),
+                    message,
+                };
+                if let Some(&(other, other_line)) = system_locations.get(&name) {
+                    problems.push(problem(format!(
+                        "already defined at {}:{}",
+                        files[other].display(),
+                        other_line
+                    )));
+                    continue;
+                }
+                system_locations.insert(name.clone(), (index, line));
+                match value.into_inner().try_into::<System>() {
+                    Ok(system) => {
+                        systems.insert(name, system);
+                    }
+                    Err(e) => problems.push(problem(e.message().to_string())),
+                }
+            }
+        }
+
+        let mut rfid_cards = HashMap::new();
+        let mut card_locations: HashMap<String, (usize, usize)> = HashMap::new();
+        for (index, (source, raw)) in sources.into_iter().enumerate() {
+            for (id, value) in raw.rfid_cards {
+                let line = line_of(&source, value.span().start);
+                let problem = |message: String| Problem {
+                    file: files[index].clone(),
+                    line: Some(line),
+                    subject: Some(format!("card {}", id)),
+                    message,
+                };
+                if let Some(&(other, other_line)) = card_locations.get(&id) {
+                    problems.push(problem(format!(
+                        "already defined at {}:{}",
+                        files[other].display(),
+                        other_line
+                    )));
+                    continue;
+                }
+                let card = value
+                    .into_inner()
+                    .try_into::<RawCardInfo>()
+                    .map_err(|e| e.message().to_string())
+                    .and_then(|raw| raw.resolve(&id, &systems));
+                match card {
+                    Ok(card) => {
+                        rfid_cards.insert(id.clone(), card);
+                    }
+                    Err(message) => problems.push(problem(message)),
+                }
+                card_locations.insert(id, (index, line));
+            }
+        }
+
+        let config = Self {
+            launcher,
+            limits,
+            devices,
+            rfid_cards,
+            files,
+            card_locations,
+        };
+        Ok((config, problems))
+    }
+
+    /// Check every card against the filesystem: ROMs, cores, programs and
+    /// working directories exist, and artwork decodes. Returns all problems found.
+    pub fn check(&self) -> Vec<Problem> {
+        let mut cards: Vec<_> = self.rfid_cards.iter().collect();
+        cards.sort_by_key(|(id, _)| self.card_locations.get(*id));
+        cards
+            .into_iter()
+            .flat_map(|(id, card)| {
+                let (index, line) = self.card_locations[id];
+                card.check().into_iter().map(move |message| Problem {
+                    file: self.files[index].clone(),
+                    line: Some(line),
+                    subject: Some(format!("card {}", id)),
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+impl CardInfo {
+    /// Whether taking the card off the reader stops its game.
+    pub fn stops_on_removal(&self, launcher: &LauncherSettings) -> bool {
+        self.stop_on_removal.unwrap_or(launcher.stop_on_removal)
+    }
+
+    pub fn launch_mode(&self, launcher: &LauncherSettings) -> LaunchMode {
+        self.launch_mode.unwrap_or(launcher.launch_mode)
+    }
+
+    pub fn launch_delay(&self, launcher: &LauncherSettings) -> Duration {
+        self.launch_delay.unwrap_or(launcher.launch_delay)
+    }
+
+    /// How long the card stays selected after a tap, or None to keep it until
+    /// another card is tapped.
+    pub fn preview_timeout(&self, launcher: &LauncherSettings) -> Option<Duration> {
+        let timeout = self.preview_timeout.unwrap_or(launcher.preview_timeout);
+        (!timeout.is_zero()).then_some(timeout)
+    }
+
+    /// The first missing file or program the card needs to start at all, as a
+    /// reason to show the player instead of launching.
+    pub fn missing_content(&self) -> Option<String> {
+        match &self.launch {
+            LaunchTarget::RetroArch { core, content, .. } => {
+                let files = match content {
+                    Content::Rom(rom_path) => vec![rom_path],
+                    Content::Discs(discs) => discs.iter().collect(),
+                };
+                if let Some(file) = files.into_iter().find(|f| !Path::new(f).is_file()) {
+                    return Some(format!("game file {} is missing", file));
+                }
+                if !Path::new(core).is_file() {
+                    return Some(format!("emulator core {} is missing", core));
+                }
+            }
+            LaunchTarget::Command { command } => {
+                if !program_exists(&command[0]) {
+                    return Some(format!("command {} not found", command[0]));
+                }
+            }
+        }
+        None
+    }
+
+    fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match &self.launch {
+            LaunchTarget::RetroArch {
+                core,
+                content,
+                append_config,
+                ..
+            } => {
+                match content {
+                    Content::Rom(rom_path) => {
+                        check_file(&mut problems, "rom_path", rom_path);
+                        if rom_path.ends_with(".m3u") {
+                            check_playlist(&mut problems, rom_path);
+                        }
+                    }
+                    Content::Discs(discs) => {
+                        for disc in discs {
+                            check_file(&mut problems, "disc", disc);
+                        }
+                    }
+                }
+                check_file(&mut problems, "emulator", core);
+                for path in append_config {
+                    check_file(&mut problems, "append_config", path);
+                }
+            }
+            LaunchTarget::Command { command } => {
+                if !program_exists(&command[0]) {
+                    problems.push(format!("command {} not found", command[0]));
+                }
+            }
+        }
+        if let Some(dir) = &self.working_dir {
+            if !Path::new(dir).is_dir() {
+                problems.push(format!("working_dir {} is not a directory", dir));
+            }
+        }
+        if let Err(e) = image::open(&self.artwork) {
+            problems.push(format!("artwork {} can't be loaded: {}", self.artwork, e));
+        }
+        problems
+    }
+}
+
+/// Parse a table that only the main config may set, such as `[launcher]`.
+/// Falls back to defaults (recording a problem) if it's invalid or misplaced.
+fn main_table<T: DeserializeOwned + Default>(
+    files: &[PathBuf],
+    sources: &mut [(String, RawConfig)],
+    problems: &mut Vec<Problem>,
+    name: &str,
+    take: impl Fn(&mut RawConfig) -> Option<Spanned<toml::Value>>,
+) -> T {
+    let mut table = T::default();
+    for (index, (source, raw)) in sources.iter_mut().enumerate() {
+        let Some(value) = take(raw) else {
+            continue;
+        };
+        let line = line_of(source, value.span().start);
+        let problem = |message: String| Problem {
+            file: files[index].clone(),
+            line: Some(line),
+            subject: Some(name.to_string()),
+            message,
+        };
+        if index > 0 {
+            problems.push(problem("can only be set in the main config".to_string()));
+            continue;
+        }
+        match value.into_inner().try_into::<T>() {
+            Ok(parsed) => table = parsed,
+            Err(e) => problems.push(problem(e.message().to_string())),
+        }
+    }
+    table
+}
+
+/// Read and parse one config file.
+fn read_source(file: &Path) -> Result<(String, RawConfig), ConfigError> {
+    let source = fs::read_to_string(file).map_err(|e| ConfigError::Read(file.to_path_buf(), e))?;
+    let parse = |e| ConfigError::Parse(file.to_path_buf(), e);
+    let mut raw: RawConfig = toml::from_str(&source).map_err(parse)?;
+    // Parsed again for just the keys, so unknown ones can be reported by line
+    let keys: HashMap<Spanned<String>, IgnoredAny> = toml::from_str(&source).map_err(parse)?;
+    raw.unknown = keys
+        .into_keys()
+        .filter(|key| !TOP_LEVEL_KEYS.contains(&key.get_ref().as_str()))
+        .collect();
+    Ok((source, raw))
+}
+
+/// The directory of drop-in fragments for the config at `path`.
+pub fn fragment_dir(path: &Path) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join("config.d")
+}
+
+/// `*.toml` files in the fragment directory, in name order. A missing
+/// directory just means there are no fragments.
+fn fragment_paths(path: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+    let dir = fragment_dir(path);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ConfigError::Read(dir, e)),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml") && path.is_file())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn check_file(problems: &mut Vec<String>, field: &str, path: &str) {
+    if !Path::new(path).is_file() {
+        problems.push(format!("{} {} does not exist", field, path));
+    }
+}
+
+/// Check that every entry of an existing `.m3u` exists. Entries are relative
+/// to the playlist's directory, as RetroArch resolves them.
+fn check_playlist(problems: &mut Vec<String>, path: &str) {
+    let Ok(playlist) = fs::read_to_string(path) else {
+        return;
+    };
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    for entry in playlist.lines().map(str::trim) {
+        if entry.is_empty() || entry.starts_with('#') {
+            continue;
+        }
+        if !dir.join(entry).is_file() {
+            problems.push(format!("playlist {} entry {} does not exist", path, entry));
+        }
+    }
+}
+
+/// Resolve a program the way `Command` would: paths are checked directly,
+/// bare names are looked up in `PATH`.
+fn program_exists(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Deserialize a duration given in (possibly fractional) seconds.
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let secs = f64::deserialize(deserializer)?;
+    Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+}
+
+/// A card's optional duration field, given in seconds.
+fn optional_seconds(field: &str, secs: Option<f64>) -> Result<Option<Duration>, String> {
+    secs.map(|secs| Duration::try_from_secs_f64(secs).map_err(|e| format!("{}: {}", field, e)))
+        .transpose()
+}
+
+/// Render an inline setting the way retroarch.cfg spells it.
+fn setting_value(key: &str, value: toml::Value) -> Result<String, String> {
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!(
+            "setting {:?} must only contain letters, digits and underscores",
+            key
+        ));
+    }
+    let value = match value {
+        toml::Value::String(s) => s,
+        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        _ => {
+            return Err(format!(
+                "setting {} must be a string, boolean or number",
+                key
+            ))
+        }
+    };
+    if value.contains(['"', '\n']) {
+        return Err(format!("setting {} can't contain quotes or newlines", key));
+    }
+    Ok(value)
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Deserialize)]
+    struct Entry {
+        card: toml::Value,
+    }
+
+    /// Resolve a card from the fields of its inline table, with no systems defined.
+    fn card(fields: &str) -> Result<CardInfo, String> {
+        let entry: Entry = toml::from_str(&format!("card = {{ {} }}", fields))
+            .map_err(|e| e.message().to_string())?;
+        entry
+            .card
+            .try_into::<RawCardInfo>()
+            .map_err(|e| e.message().to_string())?
+            .resolve("0001", &HashMap::new())
+    }
+
+    /// A config directory in the temp dir, removed again on drop.
+    struct ConfigDir(PathBuf);
+
+    impl ConfigDir {
+        /// Write `main` as config.toml and each `(name, contents)` fragment
+        /// into config.d/.
+        fn new(main: &str, fragments: &[(&str, &str)]) -> Self {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let count = COUNT.fetch_add(1, Ordering::SeqCst);
+            let dir = std::env::temp_dir().join(format!(
+                "bgc-config-test-{}-{}",
+                std::process::id(),
+                count
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("config.d")).unwrap();
+            fs::write(dir.join("config.toml"), main).unwrap();
+            for (name, contents) in fragments {
+                fs::write(dir.join("config.d").join(name), contents).unwrap();
+            }
+            Self(dir)
+        }
+
+        fn main(&self) -> PathBuf {
+            self.0.join("config.toml")
+        }
+
+        /// Load with `load_partial`, with problems in file order and their
+        /// paths relative to the directory.
+        fn load(&self) -> (Config, Vec<String>) {
+            let (config, mut problems) = Config::load_partial(&self.main()).unwrap();
+            config.sort_problems(&mut problems);
+            let prefix = format!("{}/", self.0.display());
+            let problems = problems
+                .iter()
+                .map(|problem| problem.to_string().replace(&prefix, ""))
+                .collect();
+            (config, problems)
+        }
+    }
+
+    impl Drop for ConfigDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn core(config: &Config, id: &str) -> String {
+        match &config.rfid_cards[id].launch {
+            LaunchTarget::RetroArch { core, .. } => core.clone(),
+            other => panic!("expected a retroarch launch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fragments_add_cards_and_systems() {
+        let dir = ConfigDir::new(
+            r#"
+[systems.snes]
+core = "snes.so"
+
+[rfid_cards.0001]
+system = "gb"
+rom_path = "tetris.gb"
+artwork = "tetris.png"
+"#,
+            &[
+                (
+                    "a-gb.toml",
+                    "[systems.gb]\ncore = \"gb.so\"\n",
+                ),
+                (
+                    "b-cards.toml",
+                    "[rfid_cards.0002]\nsystem = \"snes\"\nrom_path = \"zelda.sfc\"\nartwork = \"zelda.png\"\n",
+                ),
+                ("notes.txt", "not a config"),
+            ],
+        );
+        let (config, problems) = dir.load();
+        assert_eq!(problems, Vec::<String>::new());
+        assert_eq!(config.rfid_cards.len(), 2);
+        // Cards can use systems from any file
+        assert_eq!(core(&config, "0001"), "gb.so");
+        assert_eq!(core(&config, "0002"), "snes.so");
+    }
+
+    #[test]
+    fn duplicates_across_files_keep_the_first_in_file_order() {
+        let card = |artwork: &str| {
+            format!(
+                "[rfid_cards.0001]\ncommand = [\"true\"]\nartwork = \"{}\"\n",
+                artwork
+            )
+        };
+        let system = "[systems.snes]\ncore = \"other.so\"\n";
+        let dir = ConfigDir::new(
+            &format!("{}\n[systems.snes]\ncore = \"snes.so\"\n", card("main.png")),
+            &[
+                ("b.toml", &format!("{}\n{}", system, card("b.png"))),
+                ("a.toml", &card("a.png")),
+            ],
+        );
+        let (config, problems) = dir.load();
+        assert_eq!(
+            problems,
+            [
+                "config.d/a.toml:1: card 0001: already defined at config.toml:1",
+                "config.d/b.toml:1: system snes: already defined at config.toml:5",
+                "config.d/b.toml:4: card 0001: already defined at config.toml:1",
+            ]
+        );
+        assert_eq!(config.rfid_cards["0001"].artwork, "main.png");
+        assert!(Config::load(&dir.main()).is_err());
+    }
+
+    #[test]
+    fn main_only_tables_are_rejected_in_fragments() {
+        let dir = ConfigDir::new(
+            "[launcher]\ngrace_period = 2\n",
+            &[(
+                "a.toml",
+                "[launcher]\ngrace_period = 9\n\n[limits]\ndaily_minutes = 1\n\n[devices]\n",
+            )],
+        );
+        let (config, problems) = dir.load();
+        assert_eq!(
+            problems,
+            [
+                "config.d/a.toml:1: launcher: can only be set in the main config",
+                "config.d/a.toml:4: limits: can only be set in the main config",
+                "config.d/a.toml:7: devices: can only be set in the main config",
+            ]
+        );
+        assert_eq!(config.launcher.grace_period, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn load_partial_skips_broken_fragments_and_cards() {
+        let dir = ConfigDir::new(
+            "[rfid_cards.0001]\ncommand = [\"true\"]\nartwork = \"a.png\"\n",
+            &[
+                ("a.toml", "[rfid_cards.0002\n"),
+                (
+                    "b.toml",
+                    "[rfid_cards.0003]\ncommand = []\nartwork = \"c.png\"\n\n[rfid_cards.0004]\ncommand = [\"true\"]\nartwork = \"d.png\"\n",
+                ),
+            ],
+        );
+        let (config, problems) = dir.load();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(
+            problems[0].starts_with("config.d/a.toml:1: failed to parse: "),
+            "{}",
+            problems[0]
+        );
+        assert_eq!(
+            problems[1],
+            "config.d/b.toml:1: card 0003: command must not be empty"
+        );
+        let mut ids: Vec<_> = config.rfid_cards.keys().collect();
+        ids.sort();
+        assert_eq!(ids, ["0001", "0004"]);
+
+        // Strict loading fails on the first broken fragment
+        assert!(matches!(
+            Config::load(&dir.main()),
+            Err(ConfigError::Parse(path, _)) if path.ends_with("config.d/a.toml")
+        ));
+    }
+
+    #[test]
+    fn broken_main_config_is_an_error_even_when_skipping() {
+        let dir = ConfigDir::new("[launcher\n", &[]);
+        assert!(matches!(
+            Config::load_partial(&dir.main()),
+            Err(ConfigError::Parse(..))
+        ));
+    }
+
+    #[test]
+    fn unknown_top_level_keys_are_reported() {
+        let dir = ConfigDir::new(
+            "[launcher]\n\n[rfid_card.0001]\ncommand = [\"true\"]\n",
+            &[("a.toml", "title = \"games\"\n")],
+        );
+        let (_, problems) = dir.load();
+        assert_eq!(
+            problems,
+            [
+                "config.toml:3: rfid_card: unknown top-level key, expected one of launcher, limits, devices, systems, rfid_cards",
+                "config.d/a.toml:1: title: unknown top-level key, expected one of launcher, limits, devices, systems, rfid_cards",
+            ]
+        );
+    }
+
+    #[test]
+    fn original_rom_card_layout_still_loads() {
+        let card = card(
+            r#"rom_path = "/roms/zelda.sfc", emulator = "/cores/snes9x_libretro.so", artwork = "assets/zelda.png""#,
+        )
+        .unwrap();
+        match &card.launch {
+            LaunchTarget::RetroArch {
+                core,
+                content: Content::Rom(rom_path),
+                append_config,
+                args,
+                settings,
+                resume,
+            } => {
+                assert_eq!(core, "/cores/snes9x_libretro.so");
+                assert_eq!(rom_path, "/roms/zelda.sfc");
+                assert!(append_config.is_empty() && args.is_empty() && settings.is_empty());
+                assert!(!resume);
+            }
+            other => panic!("expected a rom launch, got {:?}", other),
+        }
+        assert_eq!(card.artwork, "assets/zelda.png");
+    }
+
+    #[test]
+    fn original_command_card_layout_still_loads() {
+        let card = card(
+            r#"command = ["/opt/apps/paint", "--fullscreen"], working_dir = "/opt/apps", artwork = "assets/paint.png""#,
+        )
+        .unwrap();
+        assert!(matches!(
+            &card.launch,
+            LaunchTarget::Command { command } if command == &["/opt/apps/paint", "--fullscreen"]
+        ));
+        assert_eq!(card.working_dir.as_deref(), Some("/opt/apps"));
+    }
+
+    #[test]
+    fn mixed_launch_targets_are_rejected() {
+        for (fields, error) in [
+            (
+                r#"command = ["true"], emulator = "snes.so""#,
+                "command cannot be combined with rom_path, discs, emulator or system",
+            ),
+            (
+                r#"command = ["true"], rom_path = "a.sfc""#,
+                "command cannot be combined with rom_path, discs, emulator or system",
+            ),
+            (
+                r#"launch = "retroarch", command = ["true"], rom_path = "a.sfc", emulator = "snes.so""#,
+                "command cannot be combined with rom_path or emulator",
+            ),
+            (
+                r#"rom_path = "a.cue", discs = ["b.cue"], emulator = "psx.so""#,
+                "rom_path cannot be combined with discs",
+            ),
+            (
+                r#"discs = [], emulator = "psx.so""#,
+                "discs must not be empty",
+            ),
+            ("command = []", "command must not be empty"),
+            (
+                r#"launch = "command""#,
+                "command is required for a command launch",
+            ),
+            (
+                r#"rom_path = "a.sfc""#,
+                "emulator or system is required for a retroarch launch",
+            ),
+            (
+                r#"emulator = "snes.so""#,
+                "rom_path or discs is required for a retroarch launch",
+            ),
+            (
+                r#"command = ["true"], args = ["-v"]"#,
+                "append_config, args, settings and resume only apply to retroarch launches",
+            ),
+            ("", "must set either command or rom_path + emulator"),
+        ] {
+            let fields = format!(r#"artwork = "a.png", {}"#, fields);
+            let fields = fields.trim_end_matches(", ");
+            assert_eq!(card(fields).unwrap_err(), error, "fields {}", fields);
+        }
+    }
+
+    fn settings(card: &CardInfo) -> &BTreeMap<String, String> {
+        match &card.launch {
+            LaunchTarget::RetroArch { settings, .. } => settings,
+            other => panic!("expected a retroarch launch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn inline_settings_are_rendered_for_retroarch_cfg() {
+        let settings_table = r#"{ video_smooth = true, audio_latency = 64, menu_driver = "rgui" }"#;
+        let card = card(&format!(
+            r#"rom_path = "a.sfc", emulator = "snes.so", artwork = "a.png", settings = {}"#,
+            settings_table
+        ))
+        .unwrap();
+        let expected = [
+            ("audio_latency", "64"),
+            ("menu_driver", "rgui"),
+            ("video_smooth", "true"),
+        ];
+        assert!(settings(&card)
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .eq(expected));
+    }
+
+    #[test]
+    fn setting_keys_and_values_cant_break_out_of_their_line() {
+        let base = r#"rom_path = "a.sfc", emulator = "snes.so", artwork = "a.png""#;
+        for (settings, error) in [
+            (
+                r#"{ "a\nb" = "x" }"#,
+                r#"setting "a\nb" must only contain letters, digits and underscores"#,
+            ),
+            (
+                r#"{ "a = 1 #" = "x" }"#,
+                r#"setting "a = 1 #" must only contain letters, digits and underscores"#,
+            ),
+            (
+                r#"{ "" = "x" }"#,
+                r#"setting "" must only contain letters, digits and underscores"#,
+            ),
+            (
+                r#"{ a = "x\"y" }"#,
+                "setting a can't contain quotes or newlines",
+            ),
+            (
+                r#"{ a = "x\ny" }"#,
+                "setting a can't contain quotes or newlines",
+            ),
+            (
+                "{ a = [1] }",
+                "setting a must be a string, boolean or number",
+            ),
+        ] {
+            let fields = format!("{}, settings = {}", base, settings);
+            assert_eq!(card(&fields).unwrap_err(), error, "settings {}", settings);
+        }
     }
 }
@@ -0,0 +1,121 @@
+use crate::config::{fragment_dir, Config};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor};
+use std::path::{Path, PathBuf};
+
+/// Watches the config file and its `config.d/` fragments for changes and hands
+/// freshly loaded configs to a callback.
+///
+/// Directories are watched rather than the files themselves, since editors
+/// commonly save by writing a temp file and renaming it over the original (which
+/// would silently orphan a watch on the old inode).
+pub struct ConfigWatcher {
+    path: PathBuf,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn run<F>(&self, mut on_reload: F)
+    where
+        F: FnMut(Config),
+    {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let fragments = fragment_dir(&self.path);
+        let file_name = self.path.file_name();
+        let fragments_name = fragments.file_name();
+
+        let inotify = match Inotify::init(InitFlags::empty()) {
+            Ok(inotify) => inotify,
+            Err(e) => {
+                eprintln!(
+                    "[config] failed to init inotify: {}, hot reload disabled",
+                    e
+                );
+                return;
+            }
+        };
+        let dir_flags =
+            AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO | AddWatchFlags::IN_CREATE;
+        if let Err(e) = inotify.add_watch(dir, dir_flags) {
+            eprintln!(
+                "[config] failed to watch {}: {}, hot reload disabled",
+                dir.display(),
+                e
+            );
+            return;
+        }
+        let mut fragments_wd = watch_fragments(&inotify, &fragments);
+        eprintln!("[config] watching {} for changes", self.path.display());
+
+        loop {
+            let events = match inotify.read_events() {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("[config] inotify error: {}, hot reload disabled", e);
+                    return;
+                }
+            };
+            let mut changed = false;
+            for event in &events {
+                if Some(event.wd) == fragments_wd && event.mask.contains(AddWatchFlags::IN_IGNORED)
+                {
+                    // config.d was removed, taking its watch with it
+                    fragments_wd = None;
+                    changed = true;
+                } else if Some(event.wd) == fragments_wd {
+                    // Any fragment added, changed or removed
+                    changed |= event
+                        .name
+                        .as_deref()
+                        .is_some_and(|name| Path::new(name).extension() == Some("toml".as_ref()));
+                } else if event.name.as_deref() == fragments_name {
+                    // config.d appeared after startup
+                    if fragments_wd.is_none() {
+                        fragments_wd = watch_fragments(&inotify, &fragments);
+                        changed = true;
+                    }
+                } else if event.name.as_deref() == file_name {
+                    changed = true;
+                }
+            }
+            if !changed {
+                continue;
+            }
+            match Config::load(&self.path) {
+                Ok(config) => {
+                    eprintln!(
+                        "[config] reloaded, {} cards configured",
+                        config.rfid_cards.len()
+                    );
+                    for problem in config.check() {
+                        eprintln!("[config] warning: {}", problem);
+                    }
+                    on_reload(config);
+                }
+                Err(e) => eprintln!("[config] {}, keeping previous cards", e),
+            }
+        }
+    }
+}
+
+fn watch_fragments(inotify: &Inotify, dir: &Path) -> Option<WatchDescriptor> {
+    if !dir.is_dir() {
+        return None;
+    }
+    let flags = AddWatchFlags::IN_CLOSE_WRITE
+        | AddWatchFlags::IN_MOVED_TO
+        | AddWatchFlags::IN_MOVED_FROM
+        | AddWatchFlags::IN_DELETE;
+    match inotify.add_watch(dir, flags) {
+        Ok(wd) => Some(wd),
+        Err(e) => {
+            eprintln!("[config] failed to watch {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
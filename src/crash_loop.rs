@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Failed launches of one card within this long of each other count towards a crash loop.
+static CRASH_WINDOW: Duration = Duration::from_secs(120);
+/// How many failed launches within the window mark a card as broken.
+static CRASH_THRESHOLD: usize = 3;
+
+/// A launch that failed to start or exited with an error right away.
+struct Failure {
+    at: Instant,
+    reason: String,
+    log: Option<PathBuf>,
+}
+
+/// Tracks failed launches per card and marks cards that keep failing as broken,
+/// so tapping them again doesn't just flash the game and the menu. Kept in
+/// memory only: a restart or config reload gives every card another chance.
+#[derive(Default)]
+pub struct CrashTracker {
+    failures: HashMap<String, Vec<Failure>>,
+    broken: HashMap<String, String>,
+}
+
+impl CrashTracker {
+    /// Record a failed launch of `card` at `now`. Returns a diagnostic summary
+    /// if this failure marked the card as broken.
+    pub fn record_failure(
+        &mut self,
+        card: &str,
+        reason: &str,
+        log: Option<PathBuf>,
+        now: Instant,
+    ) -> Option<String> {
+        let failures = self.failures.entry(card.to_string()).or_default();
+        failures.retain(|f| now.duration_since(f.at) < CRASH_WINDOW);
+        failures.push(Failure {
+            at: now,
+            reason: reason.to_string(),
+            log,
+        });
+        if failures.len() < CRASH_THRESHOLD || self.broken.contains_key(card) {
+            return None;
+        }
+
+        let mut summary = format!(
+            "card={} failed {} times in {:.0?}:",
+            card,
+            failures.len(),
+            now.duration_since(failures[0].at)
+        );
+        for failure in failures.iter() {
+            summary.push_str(&format!("\n  {}", failure.reason));
+            if let Some(log) = &failure.log {
+                summary.push_str(&format!(" (log {})", log.display()));
+            }
+        }
+        self.broken.insert(card.to_string(), reason.to_string());
+        Some(summary)
+    }
+
+    /// A launch that ran normally resets the card's failure count.
+    pub fn record_success(&mut self, card: &str) {
+        self.failures.remove(card);
+    }
+
+    /// The last failure reason of a card marked as broken.
+    pub fn broken(&self, card: &str) -> Option<&str> {
+        self.broken.get(card).map(String::as_str)
+    }
+
+    /// Forget every failure, e.g. after the config changed.
+    pub fn clear(&mut self) {
+        self.failures.clear();
+        self.broken.clear();
+    }
+}
@@ -0,0 +1,200 @@
+use crate::readers::ReaderConfig;
+use evdev::Device;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+static INPUT_DIR: &str = "/dev/input";
+/// Only used if inotify isn't available.
+static POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Input devices to listen to, from the `[devices]` table.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Devices {
+    pub power_button: DeviceMatch,
+    /// Every reader feeds the same card handler.
+    pub rfid_readers: Vec<ReaderConfig>,
+}
+
+impl Default for Devices {
+    fn default() -> Self {
+        Self {
+            power_button: DeviceMatch::by_name("Power Button"),
+            rfid_readers: vec![ReaderConfig::default()],
+        }
+    }
+}
+
+/// Identifies an evdev device. Every field given must match, so identical
+/// readers can be told apart by their physical path.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "RawDeviceMatch")]
+pub struct DeviceMatch {
+    name: Option<String>,
+    vendor: Option<u16>,
+    product: Option<u16>,
+    phys: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawDeviceMatch {
+    name: Option<String>,
+    vendor: Option<u16>,
+    product: Option<u16>,
+    phys: Option<String>,
+}
+
+impl TryFrom<RawDeviceMatch> for DeviceMatch {
+    type Error = String;
+
+    fn try_from(raw: RawDeviceMatch) -> Result<Self, Self::Error> {
+        Self::new(raw.name, raw.vendor, raw.product, raw.phys)
+    }
+}
+
+impl DeviceMatch {
+    pub fn new(
+        name: Option<String>,
+        vendor: Option<u16>,
+        product: Option<u16>,
+        phys: Option<String>,
+    ) -> Result<Self, String> {
+        if name.is_none() && vendor.is_none() && product.is_none() && phys.is_none() {
+            return Err("device needs at least one of name, vendor, product or phys".to_string());
+        }
+        Ok(Self {
+            name,
+            vendor,
+            product,
+            phys,
+        })
+    }
+
+    pub fn by_name(name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            vendor: None,
+            product: None,
+            phys: None,
+        }
+    }
+
+    pub fn matches(&self, device: &Device) -> bool {
+        let id = device.input_id();
+        self.name
+            .as_deref()
+            .is_none_or(|n| device.name() == Some(n))
+            && self.vendor.is_none_or(|v| id.vendor() == v)
+            && self.product.is_none_or(|p| id.product() == p)
+            && self
+                .phys
+                .as_deref()
+                .is_none_or(|p| device.physical_path() == Some(p))
+    }
+}
+
+impl fmt::Display for DeviceMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(name) = &self.name {
+            parts.push(format!("name={:?}", name));
+        }
+        if let Some(vendor) = self.vendor {
+            parts.push(format!("vendor={:04x}", vendor));
+        }
+        if let Some(product) = self.product {
+            parts.push(format!("product={:04x}", product));
+        }
+        if let Some(phys) = &self.phys {
+            parts.push(format!("phys={:?}", phys));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Block until a matching device is present, and return it opened. Devices
+/// already plugged in are checked first; after that only nodes udev creates or
+/// updates in `/dev/input` are looked at, so a missing device costs nothing
+/// until it appears. Removal shows up as a read error on the open device.
+pub fn wait_for_device(matcher: &DeviceMatch) -> (PathBuf, Device) {
+    // Watch before scanning, so a device plugged in between the two isn't missed.
+    // Nodes appear root-only and are chmod'ed by udev, hence IN_ATTRIB.
+    let inotify = Inotify::init(InitFlags::empty()).and_then(|inotify| {
+        inotify.add_watch(
+            INPUT_DIR,
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB,
+        )?;
+        Ok(inotify)
+    });
+    if let Some(found) = find_device(matcher) {
+        return found;
+    }
+    eprintln!("[devices] waiting for {}", matcher);
+    let inotify = match inotify {
+        Ok(inotify) => inotify,
+        Err(e) => {
+            eprintln!(
+                "[devices] failed to watch {}: {}, polling instead",
+                INPUT_DIR, e
+            );
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                if let Some(found) = find_device(matcher) {
+                    return found;
+                }
+            }
+        }
+    };
+    loop {
+        let events = match inotify.read_events() {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("[devices] inotify error: {}, retrying...", e);
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        for event in events {
+            let Some(name) = event.name else {
+                continue;
+            };
+            if let Some(found) = open_matching(&Path::new(INPUT_DIR).join(name), matcher) {
+                return found;
+            }
+        }
+    }
+}
+
+/// The first event device in `/dev/input` that matches.
+fn find_device(matcher: &DeviceMatch) -> Option<(PathBuf, Device)> {
+    let entries = match fs::read_dir(INPUT_DIR) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("[devices] failed to read {}: {}", INPUT_DIR, e);
+            return None;
+        }
+    };
+    entries
+        .flatten()
+        .find_map(|entry| open_matching(&entry.path(), matcher))
+}
+
+fn open_matching(path: &Path, matcher: &DeviceMatch) -> Option<(PathBuf, Device)> {
+    let is_event = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("event"));
+    if !is_event {
+        return None;
+    }
+    let device = Device::open(path).ok()?;
+    matcher
+        .matches(&device)
+        .then(|| (path.to_path_buf(), device))
+}
@@ -0,0 +1,798 @@
+use crate::config::{CardInfo, Config, LaunchMode};
+use crate::crash_loop::CrashTracker;
+use crate::readers::ReaderEvent;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How soon after a game starts its play-time warning may be shown at the
+/// earliest, giving RetroArch a moment to start listening for it.
+static FIRST_WARNING: Duration = Duration::from_secs(10);
+
+/// Everything the launcher reacts to, sent to the event loop by the device,
+/// game and config threads.
+#[derive(Debug)]
+pub enum Event {
+    Reader(ReaderEvent),
+    PowerButton,
+    /// The deadline from `EventLoop::deadline` may have passed.
+    Timer,
+    /// A requested launch was refused, e.g. by play-time limits.
+    LaunchRefused,
+    /// A game was spawned, with the play time it's allowed if limited.
+    GameStarted {
+        game: RunningGame,
+        time_left: Option<Duration>,
+    },
+    /// The game thread is done with a card. `failure` is set if it couldn't be
+    /// started or quit with an error right away.
+    GameExited {
+        card: CardInfo,
+        failure: Option<String>,
+        log: Option<PathBuf>,
+    },
+    /// The menu is up and can take taps again.
+    MenuShown,
+    ConfigReloaded,
+}
+
+/// What the event loop asks its owner to do. Nothing here has happened yet.
+#[derive(Debug)]
+pub enum Action {
+    ShowCard {
+        artwork: String,
+        countdown: Option<Duration>,
+    },
+    ClearPreview,
+    ShowFailure(LaunchFailure),
+    /// Launch a card, unless play time or missing files say otherwise (in which
+    /// case `LaunchRefused` should follow). The menu makes way for the game.
+    Launch(CardInfo),
+    /// Bring the menu back after a game; `MenuShown` should follow.
+    ShowMenu,
+    /// Tell the player something on the game's screen, if it can show messages.
+    Warn {
+        game: RunningGame,
+        message: String,
+    },
+    /// Ask the game to quit. A `Timer` kills it if it hasn't once the grace
+    /// period is up.
+    Stop(RunningGame),
+    Kill(RunningGame),
+}
+
+/// A spawned game process and the card that launched it.
+#[derive(Debug, Clone)]
+pub struct RunningGame {
+    pub pid: u32,
+    pub card: CardInfo,
+}
+
+/// A card that couldn't be started, and why.
+#[derive(Debug)]
+pub struct LaunchFailure {
+    pub card: CardInfo,
+    pub reason: String,
+}
+
+impl LaunchFailure {
+    pub fn message(&self) -> String {
+        format!("Couldn't start the game: {}", self.reason)
+    }
+}
+
+/// Where the launcher is between taps, launches and exits.
+enum Phase {
+    /// The menu isn't up yet, at startup or after a game. Taps are ignored.
+    WaitingForMenu,
+    /// The menu is up, with a card selected or not.
+    Menu(Option<Selection>),
+    /// A launch was requested and the game hasn't started yet.
+    Launching,
+    /// A game is running.
+    Playing {
+        game: RunningGame,
+        limit: Option<TimeLimit>,
+        stopping: Stopping,
+    },
+}
+
+/// A tapped card waiting for the power button, or for `deadline`.
+struct Selection {
+    card: CardInfo,
+    mode: LaunchMode,
+    deadline: Option<Instant>,
+}
+
+/// When a game's play time runs out, and when to warn about it (until it has).
+struct TimeLimit {
+    ends_at: Instant,
+    warn_at: Option<Instant>,
+}
+
+/// How far along stopping a game is.
+enum Stopping {
+    No,
+    /// Asked to quit, and killed at `kill_at` if it hasn't.
+    Asked {
+        kill_at: Instant,
+    },
+    /// Killed; all that's left is its exit.
+    Killed,
+}
+
+/// The launcher's state machine. It only decides: every event comes in with
+/// the config and time to use, and the effects come back out as actions, so
+/// the same events always lead to the same actions.
+pub struct EventLoop {
+    phase: Phase,
+    crashes: CrashTracker,
+    /// The last card read and when, for debouncing repeated reads.
+    last_read: Option<(String, Instant)>,
+}
+
+impl EventLoop {
+    pub fn new() -> Self {
+        Self {
+            phase: Phase::WaitingForMenu,
+            crashes: CrashTracker::default(),
+            last_read: None,
+        }
+    }
+
+    /// When a `Timer` event is due, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        match &self.phase {
+            Phase::Menu(Some(selection)) => selection.deadline,
+            Phase::Playing {
+                stopping: Stopping::Asked { kill_at },
+                ..
+            } => Some(*kill_at),
+            Phase::Playing {
+                stopping: Stopping::No,
+                limit: Some(limit),
+                ..
+            } => Some(limit.warn_at.unwrap_or(limit.ends_at).min(limit.ends_at)),
+            _ => None,
+        }
+    }
+
+    pub fn handle(&mut self, event: Event, config: &Config, now: Instant) -> Vec<Action> {
+        match event {
+            Event::Reader(ReaderEvent::Tapped(id)) => {
+                if self.repeated_read(&id, config, now) {
+                    return Vec::new();
+                }
+                self.card_tapped(&id, config, now)
+            }
+            Event::Reader(ReaderEvent::Removed(id)) => self.card_removed(&id, config, now),
+            Event::PowerButton => self.power_pressed(config, now),
+            Event::Timer => self.timer(config, now),
+            Event::LaunchRefused => {
+                if matches!(self.phase, Phase::Launching) {
+                    self.phase = Phase::Menu(None);
+                }
+                Vec::new()
+            }
+            Event::GameStarted { game, time_left } => {
+                self.game_started(game, time_left, config, now);
+                Vec::new()
+            }
+            Event::GameExited { card, failure, log } => self.game_exited(card, failure, log, now),
+            Event::MenuShown => {
+                if matches!(self.phase, Phase::WaitingForMenu) {
+                    self.phase = Phase::Menu(None);
+                }
+                Vec::new()
+            }
+            Event::ConfigReloaded => {
+                // The config may have fixed whatever was failing
+                self.crashes.clear();
+                Vec::new()
+            }
+        }
+    }
+
+    /// Whether `id` was already read within the debounce window. Every read
+    /// extends the window, so a reader repeating a card that sits on it counts
+    /// as one tap.
+    fn repeated_read(&mut self, id: &str, config: &Config, now: Instant) -> bool {
+        let window = config.launcher.debounce;
+        let repeated = self
+            .last_read
+            .as_ref()
+            .is_some_and(|(last_id, at)| last_id == id && now - *at < window);
+        self.last_read = Some((id.to_string(), now));
+        repeated
+    }
+
+    fn card_tapped(&mut self, id: &str, config: &Config, now: Instant) -> Vec<Action> {
+        // A game is running, or about to be, or the menu isn't back yet
+        if !matches!(self.phase, Phase::Menu(_)) {
+            return Vec::new();
+        }
+        let Some(card) = config.rfid_cards.get(id) else {
+            eprintln!("[rfid] unknown card={}", id);
+            return Vec::new();
+        };
+        if let Some(reason) = self.crashes.broken(id) {
+            eprintln!("[rfid] card={} is marked as broken: {}", id, reason);
+            self.phase = Phase::Menu(None);
+            return vec![Action::ShowFailure(LaunchFailure {
+                card: card.clone(),
+                reason: "it keeps crashing, check its launch logs".to_string(),
+            })];
+        }
+
+        let mode = card.launch_mode(&config.launcher);
+        eprintln!(
+            "[rfid] card={} artwork={} mode={:?}",
+            id, card.artwork, mode
+        );
+        if mode == LaunchMode::Instant {
+            self.phase = Phase::Launching;
+            return vec![Action::Launch(card.clone())];
+        }
+        let countdown = match mode {
+            LaunchMode::Delayed => Some(card.launch_delay(&config.launcher)),
+            // Without a timeout the card stays selected until another is tapped
+            _ => card.preview_timeout(&config.launcher),
+        };
+        self.phase = Phase::Menu(Some(Selection {
+            card: card.clone(),
+            mode,
+            deadline: countdown.map(|countdown| now + countdown),
+        }));
+        vec![Action::ShowCard {
+            artwork: card.artwork.clone(),
+            countdown,
+        }]
+    }
+
+    fn card_removed(&mut self, id: &str, config: &Config, now: Instant) -> Vec<Action> {
+        eprintln!("[rfid] card={} removed", id);
+        // Putting it back is a new tap
+        if self
+            .last_read
+            .as_ref()
+            .is_some_and(|(last_id, _)| last_id == id)
+        {
+            self.last_read = None;
+        }
+
+        match &self.phase {
+            Phase::Playing {
+                game,
+                stopping: Stopping::No,
+                ..
+            } if game.card.id == id && game.card.stops_on_removal(&config.launcher) => {
+                eprintln!("[rfid] card={} removed, stopping pid={}", id, game.pid);
+                self.stop(config, now)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn power_pressed(&mut self, config: &Config, now: Instant) -> Vec<Action> {
+        match &mut self.phase {
+            Phase::Playing {
+                game,
+                stopping: stopping @ Stopping::Asked { .. },
+                ..
+            } => {
+                // A second press skips the grace period
+                eprintln!("[power] second press, sending SIGKILL to pgid={}", game.pid);
+                *stopping = Stopping::Killed;
+                vec![Action::Kill(game.clone())]
+            }
+            Phase::Playing { .. } => self.stop(config, now),
+            Phase::Menu(selection) => match selection.take() {
+                Some(selection) => {
+                    self.phase = Phase::Launching;
+                    vec![Action::Launch(selection.card)]
+                }
+                None => Vec::new(),
+            },
+            Phase::WaitingForMenu | Phase::Launching => Vec::new(),
+        }
+    }
+
+    /// Ask the running game to quit, unless it already was.
+    fn stop(&mut self, config: &Config, now: Instant) -> Vec<Action> {
+        match &mut self.phase {
+            Phase::Playing {
+                game,
+                stopping: stopping @ Stopping::No,
+                ..
+            } => {
+                *stopping = Stopping::Asked {
+                    kill_at: now + config.launcher.grace_period,
+                };
+                vec![Action::Stop(game.clone())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn timer(&mut self, config: &Config, now: Instant) -> Vec<Action> {
+        if self.deadline().is_none_or(|deadline| now < deadline) {
+            return Vec::new();
+        }
+        match &mut self.phase {
+            Phase::Menu(selection) => {
+                let Some(selection) = selection.take() else {
+                    return Vec::new();
+                };
+                if selection.mode == LaunchMode::Delayed {
+                    self.phase = Phase::Launching;
+                    vec![Action::Launch(selection.card)]
+                } else {
+                    vec![Action::ClearPreview]
+                }
+            }
+            Phase::Playing {
+                game,
+                stopping: stopping @ Stopping::Asked { .. },
+                ..
+            } => {
+                eprintln!(
+                    "[stop] pid={} still running after {:.0?}, sending SIGKILL to pgid={}",
+                    game.pid, config.launcher.grace_period, game.pid
+                );
+                *stopping = Stopping::Killed;
+                vec![Action::Kill(game.clone())]
+            }
+            Phase::Playing {
+                game,
+                limit: Some(limit),
+                ..
+            } => {
+                if now >= limit.ends_at {
+                    eprintln!("[limits] time is up for pid={}", game.pid);
+                    return self.stop(config, now);
+                }
+                limit.warn_at = None;
+                let minutes = (limit.ends_at - now).as_secs().div_ceil(60);
+                vec![Action::Warn {
+                    game: game.clone(),
+                    message: format!("{} minutes of play left", minutes),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn game_started(
+        &mut self,
+        game: RunningGame,
+        time_left: Option<Duration>,
+        config: &Config,
+        now: Instant,
+    ) {
+        let limit = time_left.map(|time_left| {
+            eprintln!("[limits] pid={} may play for {:.0?}", game.pid, time_left);
+            let ends_at = now + time_left;
+            let warn_at = ends_at
+                .checked_sub(config.limits.warn_before())
+                .unwrap_or(now)
+                .max(now + FIRST_WARNING);
+            TimeLimit {
+                ends_at,
+                warn_at: Some(warn_at),
+            }
+        });
+        self.phase = Phase::Playing {
+            game,
+            limit,
+            stopping: Stopping::No,
+        };
+    }
+
+    fn game_exited(
+        &mut self,
+        card: CardInfo,
+        failure: Option<String>,
+        log: Option<PathBuf>,
+        now: Instant,
+    ) -> Vec<Action> {
+        let stopped = matches!(
+            self.phase,
+            Phase::Playing {
+                stopping: Stopping::Asked { .. } | Stopping::Killed,
+                ..
+            }
+        );
+        self.phase = Phase::WaitingForMenu;
+        match failure {
+            // Quitting right away after being asked to is no failure
+            Some(reason) if !stopped => {
+                let summary = self.crashes.record_failure(&card.id, &reason, log, now);
+                if let Some(summary) = summary {
+                    eprintln!("[crash] marking card as broken, {}", summary);
+                }
+                vec![
+                    Action::ShowMenu,
+                    Action::ShowFailure(LaunchFailure { card, reason }),
+                ]
+            }
+            _ => {
+                self.crashes.record_success(&card.id);
+                vec![Action::ShowMenu]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LaunchTarget;
+
+    fn card(id: &str) -> CardInfo {
+        CardInfo {
+            id: id.to_string(),
+            launch: LaunchTarget::Command {
+                command: vec!["true".to_string()],
+            },
+            working_dir: None,
+            env: Default::default(),
+            env_clear: false,
+            env_inherit: Vec::new(),
+            artwork: format!("{}.png", id),
+            stop_on_removal: None,
+            launch_mode: None,
+            launch_delay: None,
+            preview_timeout: None,
+        }
+    }
+
+    fn config(cards: &[CardInfo]) -> Config {
+        let mut config = Config::default();
+        for card in cards {
+            config.rfid_cards.insert(card.id.clone(), card.clone());
+        }
+        config
+    }
+
+    fn tap(id: &str) -> Event {
+        Event::Reader(ReaderEvent::Tapped(id.to_string()))
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    /// An event loop with the menu up.
+    fn menu(config: &Config, now: Instant) -> EventLoop {
+        let mut state = EventLoop::new();
+        state.handle(Event::MenuShown, config, now);
+        state
+    }
+
+    /// Launch `card` right away and have its game start as pid 100.
+    fn play(
+        state: &mut EventLoop,
+        card: &CardInfo,
+        time_left: Option<Duration>,
+        config: &Config,
+        now: Instant,
+    ) -> RunningGame {
+        let actions = state.handle(tap(&card.id), config, now);
+        assert!(matches!(actions[..], [Action::Launch(_)]));
+        let game = RunningGame {
+            pid: 100,
+            card: card.clone(),
+        };
+        let started = Event::GameStarted {
+            game: game.clone(),
+            time_left,
+        };
+        state.handle(started, config, now);
+        game
+    }
+
+    fn instant(id: &str) -> CardInfo {
+        let mut card = card(id);
+        card.launch_mode = Some(LaunchMode::Instant);
+        card
+    }
+
+    fn delayed(id: &str) -> CardInfo {
+        let mut card = card(id);
+        card.launch_mode = Some(LaunchMode::Delayed);
+        card
+    }
+
+    fn exited(card: &CardInfo, failure: Option<&str>) -> Event {
+        Event::GameExited {
+            card: card.clone(),
+            failure: failure.map(str::to_string),
+            log: None,
+        }
+    }
+
+    #[test]
+    fn confirm_tap_times_out() {
+        let config = config(&[card("0001")]);
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+
+        let actions = state.handle(tap("0001"), &config, start);
+        assert!(matches!(
+            &actions[..],
+            [Action::ShowCard { artwork, countdown: Some(countdown) }]
+                if artwork == "0001.png" && *countdown == secs(5)
+        ));
+        assert_eq!(state.deadline(), Some(start + secs(5)));
+        assert!(state
+            .handle(Event::Timer, &config, start + secs(4))
+            .is_empty());
+
+        let actions = state.handle(Event::Timer, &config, start + secs(5));
+        assert!(matches!(actions[..], [Action::ClearPreview]));
+        assert_eq!(state.deadline(), None);
+        // Nothing is selected for the power button anymore
+        assert!(state
+            .handle(Event::PowerButton, &config, start + secs(6))
+            .is_empty());
+    }
+
+    #[test]
+    fn power_button_launches_the_selected_card() {
+        let config = config(&[card("0001")]);
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+
+        state.handle(tap("0001"), &config, start);
+        let actions = state.handle(Event::PowerButton, &config, start + secs(1));
+        assert!(matches!(&actions[..], [Action::Launch(card)] if card.id == "0001"));
+        assert_eq!(state.deadline(), None);
+    }
+
+    #[test]
+    fn delayed_tap_launches_on_timer() {
+        let config = config(&[delayed("0001")]);
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+
+        let actions = state.handle(tap("0001"), &config, start);
+        assert!(matches!(
+            &actions[..],
+            [Action::ShowCard { countdown: Some(countdown), .. }] if *countdown == secs(3)
+        ));
+        let actions = state.handle(Event::Timer, &config, start + secs(3));
+        assert!(matches!(&actions[..], [Action::Launch(card)] if card.id == "0001"));
+        // Launching already, so the power button has nothing to do
+        assert!(state
+            .handle(Event::PowerButton, &config, start + secs(4))
+            .is_empty());
+    }
+
+    #[test]
+    fn second_card_during_delay_replaces_the_first() {
+        let config = config(&[delayed("0001"), delayed("0002")]);
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+
+        state.handle(tap("0001"), &config, start);
+        let actions = state.handle(tap("0002"), &config, start + secs(1));
+        assert!(matches!(
+            &actions[..],
+            [Action::ShowCard { artwork, .. }] if artwork == "0002.png"
+        ));
+        // The first card's deadline is gone
+        assert!(state
+            .handle(Event::Timer, &config, start + secs(3))
+            .is_empty());
+        let actions = state.handle(Event::Timer, &config, start + secs(4));
+        assert!(matches!(&actions[..], [Action::Launch(card)] if card.id == "0002"));
+    }
+
+    #[test]
+    fn repeated_reads_are_debounced() {
+        let config = config(&[card("0001")]);
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+
+        assert_eq!(state.handle(tap("0001"), &config, start).len(), 1);
+        assert!(state
+            .handle(tap("0001"), &config, start + secs(1))
+            .is_empty());
+        // Each read extends the window
+        assert!(state
+            .handle(tap("0001"), &config, start + secs(2))
+            .is_empty());
+        assert_eq!(state.handle(tap("0001"), &config, start + secs(5)).len(), 1);
+        // Taking the card away and putting it back is a new tap
+        state.handle(
+            Event::Reader(ReaderEvent::Removed("0001".to_string())),
+            &config,
+            start + secs(5),
+        );
+        assert_eq!(state.handle(tap("0001"), &config, start + secs(6)).len(), 1);
+    }
+
+    #[test]
+    fn taps_wait_for_the_menu() {
+        let config = config(&[instant("0001")]);
+        let start = Instant::now();
+        let mut state = EventLoop::new();
+
+        assert!(state.handle(tap("0001"), &config, start).is_empty());
+        state.handle(Event::MenuShown, &config, start + secs(5));
+        let actions = state.handle(tap("0001"), &config, start + secs(10));
+        assert!(matches!(actions[..], [Action::Launch(_)]));
+    }
+
+    #[test]
+    fn refused_launch_returns_to_the_menu() {
+        let config = config(&[instant("0001")]);
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+
+        state.handle(tap("0001"), &config, start);
+        state.handle(Event::LaunchRefused, &config, start);
+        let actions = state.handle(tap("0001"), &config, start + secs(10));
+        assert!(matches!(actions[..], [Action::Launch(_)]));
+    }
+
+    #[test]
+    fn removing_the_card_stops_the_game() {
+        let mut card = instant("0001");
+        card.stop_on_removal = Some(true);
+        let config = config(&[card.clone(), instant("0002")]);
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+        play(&mut state, &card, None, &config, start);
+
+        // Some other card coming and going doesn't matter
+        let removed = |id: &str| Event::Reader(ReaderEvent::Removed(id.to_string()));
+        assert!(state.handle(removed("0002"), &config, start).is_empty());
+        let actions = state.handle(removed("0001"), &config, start + secs(60));
+        assert!(matches!(&actions[..], [Action::Stop(game)] if game.pid == 100));
+        assert_eq!(state.deadline(), Some(start + secs(65)));
+
+        // Still running once the grace period is up
+        let actions = state.handle(Event::Timer, &config, start + secs(65));
+        assert!(matches!(&actions[..], [Action::Kill(game)] if game.pid == 100));
+        assert_eq!(state.deadline(), None);
+    }
+
+    #[test]
+    fn removing_the_card_is_ignored_without_stop_on_removal() {
+        let card = instant("0001");
+        let config = config(std::slice::from_ref(&card));
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+        play(&mut state, &card, None, &config, start);
+
+        let removed = Event::Reader(ReaderEvent::Removed("0001".to_string()));
+        assert!(state.handle(removed, &config, start + secs(60)).is_empty());
+    }
+
+    #[test]
+    fn second_power_press_kills_the_game() {
+        let card = instant("0001");
+        let config = config(std::slice::from_ref(&card));
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+        play(&mut state, &card, None, &config, start);
+
+        let actions = state.handle(Event::PowerButton, &config, start + secs(60));
+        assert!(matches!(actions[..], [Action::Stop(_)]));
+        let actions = state.handle(Event::PowerButton, &config, start + secs(61));
+        assert!(matches!(actions[..], [Action::Kill(_)]));
+        assert!(state
+            .handle(Event::PowerButton, &config, start + secs(62))
+            .is_empty());
+
+        // Dying from the kill isn't a failed launch
+        let actions = state.handle(
+            exited(&card, Some("it quit right away (signal: 9)")),
+            &config,
+            start + secs(62),
+        );
+        assert!(matches!(actions[..], [Action::ShowMenu]));
+    }
+
+    #[test]
+    fn time_limit_warns_then_stops_the_game() {
+        let card = instant("0001");
+        let config = config(std::slice::from_ref(&card));
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+        play(&mut state, &card, Some(secs(30 * 60)), &config, start);
+
+        // Warned the default five minutes before the end
+        assert_eq!(state.deadline(), Some(start + secs(25 * 60)));
+        let actions = state.handle(Event::Timer, &config, start + secs(25 * 60));
+        assert!(matches!(
+            &actions[..],
+            [Action::Warn { message, .. }] if message == "5 minutes of play left"
+        ));
+
+        assert_eq!(state.deadline(), Some(start + secs(30 * 60)));
+        let actions = state.handle(Event::Timer, &config, start + secs(30 * 60));
+        assert!(matches!(actions[..], [Action::Stop(_)]));
+        let actions = state.handle(Event::Timer, &config, start + secs(30 * 60 + 5));
+        assert!(matches!(actions[..], [Action::Kill(_)]));
+    }
+
+    #[test]
+    fn short_time_limit_warns_after_the_game_is_up() {
+        let card = instant("0001");
+        let config = config(std::slice::from_ref(&card));
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+        play(&mut state, &card, Some(secs(60)), &config, start);
+
+        assert_eq!(state.deadline(), Some(start + secs(10)));
+        let actions = state.handle(Event::Timer, &config, start + secs(10));
+        assert!(matches!(
+            &actions[..],
+            [Action::Warn { message, .. }] if message == "1 minutes of play left"
+        ));
+    }
+
+    #[test]
+    fn failure_before_the_game_starts_is_shown() {
+        let card = instant("0001");
+        let config = config(std::slice::from_ref(&card));
+        let start = Instant::now();
+        let mut state = menu(&config, start);
+
+        state.handle(tap("0001"), &config, start);
+        let actions = state.handle(exited(&card, Some("No such file")), &config, start);
+        assert!(matches!(
+            &actions[..],
+            [Action::ShowMenu, Action::ShowFailure(failure)] if failure.reason == "No such file"
+        ));
+    }
+
+    #[test]
+    fn card_that_dies_right_away_is_marked_broken_after_three_taps() {
+        let mut card = card("0001");
+        card.launch_mode = Some(LaunchMode::Instant);
+        let config = config(std::slice::from_ref(&card));
+        let start = Instant::now();
+        let mut state = EventLoop::new();
+        state.handle(Event::MenuShown, &config, start);
+
+        for attempt in 0..3 {
+            // Taps further apart than the debounce window
+            let now = start + secs(attempt * 10);
+            let actions = state.handle(tap("0001"), &config, now);
+            assert!(matches!(actions[..], [Action::Launch(_)]));
+            let game = RunningGame {
+                pid: 100 + attempt as u32,
+                card: card.clone(),
+            };
+            let started = Event::GameStarted {
+                game,
+                time_left: None,
+            };
+            state.handle(started, &config, now);
+            let actions = state.handle(
+                Event::GameExited {
+                    card: card.clone(),
+                    failure: Some("it quit right away (exit status: 1)".to_string()),
+                    log: None,
+                },
+                &config,
+                now + Duration::from_millis(500),
+            );
+            assert!(matches!(
+                actions[..],
+                [Action::ShowMenu, Action::ShowFailure(_)]
+            ));
+            state.handle(Event::MenuShown, &config, now + secs(1));
+        }
+
+        let actions = state.handle(tap("0001"), &config, start + secs(40));
+        match &actions[..] {
+            [Action::ShowFailure(failure)] => {
+                assert_eq!(failure.reason, "it keeps crashing, check its launch logs")
+            }
+            other => panic!("expected the broken card's failure, got {:?}", other),
+        }
+    }
+}
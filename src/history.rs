@@ -0,0 +1,227 @@
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// One launch, as recorded in the history file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Launch {
+    pub card: String,
+    pub started: DateTime<Local>,
+    pub duration_secs: f64,
+    /// Exit code, if the game exited normally.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Terminating signal, if the game was killed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// Why the game couldn't be spawned or waited on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Launch {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration_secs.max(0.0))
+    }
+}
+
+/// Play history, stored as an append-only JSONL file with one [`Launch`] per line.
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn record(&self, launch: &Launch) -> io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(launch)?;
+        line.push('\n');
+        // A single write per line, so a crash can't leave half a record behind another
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// Every recorded launch, oldest first. Lines that don't parse are skipped.
+    pub fn launches(&self) -> io::Result<Vec<Launch>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(index, line)| match serde_json::from_str(line) {
+                Ok(launch) => Some(launch),
+                Err(e) => {
+                    eprintln!(
+                        "[history] skipping {}:{}: {}",
+                        self.path.display(),
+                        index + 1,
+                        e
+                    );
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Total play time per card.
+pub fn totals_per_card(launches: &[Launch]) -> BTreeMap<String, Duration> {
+    let mut totals = BTreeMap::new();
+    for launch in launches {
+        *totals.entry(launch.card.clone()).or_default() += launch.duration();
+    }
+    totals
+}
+
+/// Total play time per local calendar day, broken down by card. A game played
+/// past midnight counts toward each day it was played on.
+pub fn totals_per_day(launches: &[Launch]) -> BTreeMap<NaiveDate, BTreeMap<String, Duration>> {
+    let mut totals: BTreeMap<NaiveDate, BTreeMap<String, Duration>> = BTreeMap::new();
+    for launch in launches {
+        for (day, duration) in split_at_midnight(launch.started, launch.duration()) {
+            *totals
+                .entry(day)
+                .or_default()
+                .entry(launch.card.clone())
+                .or_default() += duration;
+        }
+    }
+    totals
+}
+
+/// The part of `duration` from `start` on that falls on each local day.
+fn split_at_midnight(start: DateTime<Local>, duration: Duration) -> Vec<(NaiveDate, Duration)> {
+    let mut parts = Vec::new();
+    let mut start = start;
+    let mut left = duration;
+    loop {
+        let day = start.date_naive();
+        // None if midnight doesn't exist locally (a DST gap); the rest stays on this day
+        let midnight = day
+            .succ_opt()
+            .and_then(|next| next.and_hms_opt(0, 0, 0))
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest());
+        let part = match midnight {
+            Some(midnight) => left.min((midnight - start).to_std().unwrap_or_default()),
+            None => left,
+        };
+        parts.push((day, part));
+        left -= part;
+        match midnight {
+            Some(midnight) if !left.is_zero() => start = midnight,
+            _ => return parts,
+        }
+    }
+}
+
+/// Format a play time as e.g. `1h 05m` or `12m`.
+pub fn format_play_time(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn launch(card: &str, started: DateTime<Local>, minutes: u64) -> Launch {
+        Launch {
+            card: card.to_string(),
+            started,
+            duration_secs: (minutes * 60) as f64,
+            exit_code: Some(0),
+            signal: None,
+            error: None,
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn totals_per_card_add_up_every_launch() {
+        let launches = [
+            launch("zelda", at(16, 15, 0), 30),
+            launch("mario", at(16, 16, 0), 10),
+            launch("zelda", at(17, 10, 0), 45),
+        ];
+        let totals = totals_per_card(&launches);
+        assert_eq!(
+            totals.into_iter().collect::<Vec<_>>(),
+            [
+                ("mario".to_string(), minutes(10)),
+                ("zelda".to_string(), minutes(75)),
+            ]
+        );
+    }
+
+    #[test]
+    fn totals_per_day_break_down_by_card() {
+        let launches = [
+            launch("zelda", at(16, 15, 0), 30),
+            launch("mario", at(16, 16, 0), 10),
+            launch("zelda", at(16, 17, 0), 20),
+            launch("mario", at(17, 10, 0), 45),
+        ];
+        let totals = totals_per_day(&launches);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[&date(16)]["zelda"], minutes(50));
+        assert_eq!(totals[&date(16)]["mario"], minutes(10));
+        assert_eq!(totals[&date(17)]["mario"], minutes(45));
+        assert!(!totals[&date(17)].contains_key("zelda"));
+    }
+
+    #[test]
+    fn play_past_midnight_counts_toward_both_days() {
+        // 23:50 until 01:30
+        let totals = totals_per_day(&[launch("zelda", at(16, 23, 50), 100)]);
+        assert_eq!(totals[&date(16)]["zelda"], minutes(10));
+        assert_eq!(totals[&date(17)]["zelda"], minutes(90));
+    }
+
+    #[test]
+    fn play_time_is_formatted_in_hours_and_minutes() {
+        for (duration, formatted) in [
+            (Duration::ZERO, "0m"),
+            (Duration::from_secs(59), "0m"),
+            (minutes(12), "12m"),
+            (minutes(60), "1h 00m"),
+            (minutes(65), "1h 05m"),
+            (minutes(25 * 60 + 30), "25h 30m"),
+        ] {
+            assert_eq!(format_play_time(duration), formatted);
+        }
+    }
+}
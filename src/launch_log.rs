@@ -0,0 +1,91 @@
+use chrono::Local;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+/// A log file capturing one launch's stdout and stderr, named by card ID and
+/// start time (e.g. `logs/0005593265-20260214-183012.log`).
+pub struct LaunchLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl LaunchLog {
+    /// Create the log for a new launch, deleting the oldest logs in `dir` so at
+    /// most `keep` remain.
+    pub fn create(dir: &Path, card_id: &str, command: &str, keep: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        prune(dir, keep.saturating_sub(1))?;
+
+        let now = Local::now();
+        let name = format!("{}-{}.log", file_safe(card_id), now.format("%Y%m%d-%H%M%S"));
+        let path = dir.join(name);
+        // Append mode, so our trailer and the child's output never overwrite each other
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "[launch] card={} at {}", card_id, now.to_rfc3339())?;
+        writeln!(file, "[launch] {}", command)?;
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Handles for the child's stdout and stderr, both writing to this log.
+    pub fn stdio(&self) -> io::Result<(Stdio, Stdio)> {
+        Ok((
+            Stdio::from(self.file.try_clone()?),
+            Stdio::from(self.file.try_clone()?),
+        ))
+    }
+
+    /// Append how the launch ended.
+    pub fn finish(mut self, outcome: &str, elapsed: Duration) {
+        if let Err(e) = writeln!(self.file, "[exit] {} after {:.0?}", outcome, elapsed) {
+            eprintln!("[log] failed to write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// A card ID as it's used in file names. IDs read from NDEF tags can be URIs,
+/// so anything but letters, digits, `-`, `_` and `.` becomes `_`.
+pub fn file_safe(card_id: &str) -> String {
+    card_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Delete the oldest `.log` files in `dir` until at most `keep` remain.
+fn prune(dir: &Path, keep: usize) -> io::Result<()> {
+    let mut logs: Vec<_> = fs::read_dir(dir)?
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    if logs.len() <= keep {
+        return Ok(());
+    }
+    logs.sort();
+    for (_, path) in &logs[..logs.len() - keep] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_card_ids_are_made_file_safe() {
+        assert_eq!(file_safe("https://example.com/x"), "https___example.com_x");
+        assert_eq!(file_safe("../0005593265"), ".._0005593265");
+        assert_eq!(file_safe("0005593265"), "0005593265");
+    }
+}
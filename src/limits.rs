@@ -0,0 +1,282 @@
+use chrono::{DateTime, Datelike, Local, NaiveTime, Weekday};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Play-time rules from the `[limits]` table: a daily allowance and the hours
+/// games may be played on each weekday.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "RawLimits")]
+pub struct Limits {
+    daily: Option<Duration>,
+    warn_before: Duration,
+    /// Allowed windows per weekday. Weekdays not listed are unrestricted; an
+    /// empty list means no games that day.
+    hours: HashMap<Weekday, Vec<(NaiveTime, NaiveTime)>>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            daily: None,
+            warn_before: Duration::from_secs(5 * 60),
+            hours: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLimits {
+    daily_minutes: Option<u64>,
+    warn_minutes: Option<u64>,
+    #[serde(default)]
+    hours: BTreeMap<String, Vec<String>>,
+}
+
+impl TryFrom<RawLimits> for Limits {
+    type Error = String;
+
+    fn try_from(raw: RawLimits) -> Result<Self, Self::Error> {
+        let mut hours = HashMap::new();
+        for (day, ranges) in raw.hours {
+            let weekday: Weekday = day
+                .parse()
+                .map_err(|_| format!("unknown weekday {}", day))?;
+            let ranges = ranges
+                .iter()
+                .map(|range| parse_range(range))
+                .collect::<Result<_, _>>()?;
+            hours.insert(weekday, ranges);
+        }
+        let defaults = Limits::default();
+        Ok(Self {
+            daily: raw.daily_minutes.map(|m| Duration::from_secs(m * 60)),
+            warn_before: raw
+                .warn_minutes
+                .map_or(defaults.warn_before, |m| Duration::from_secs(m * 60)),
+            hours,
+        })
+    }
+}
+
+/// Whether a game may be launched right now.
+#[derive(Debug, PartialEq)]
+pub enum Allowance {
+    /// Not now; the reason is shown on screen.
+    Denied(String),
+    /// Go ahead, for at most this long (`None` means no limit applies).
+    Allowed(Option<Duration>),
+}
+
+impl Limits {
+    /// Decide whether a launch is allowed at `now`, given how much has already
+    /// been played today. The allowed time ends at whichever comes first: the
+    /// daily allowance running out or the current window closing.
+    pub fn allowance(&self, now: DateTime<Local>, played_today: Duration) -> Allowance {
+        let mut left = None;
+        if let Some(ranges) = self.hours.get(&now.weekday()) {
+            let time = now.time();
+            match ranges
+                .iter()
+                .find(|(start, end)| *start <= time && time < *end)
+            {
+                Some((_, end)) => left = Some((*end - time).to_std().unwrap_or_default()),
+                None => return Allowance::Denied(hours_message(now.weekday(), ranges)),
+            }
+        }
+        if let Some(daily) = self.daily {
+            let remaining = daily.saturating_sub(played_today);
+            if remaining.is_zero() {
+                return Allowance::Denied("That's all the games for today!".to_string());
+            }
+            left = Some(left.map_or(remaining, |left: Duration| left.min(remaining)));
+        }
+        Allowance::Allowed(left)
+    }
+
+    /// How long before time runs out to warn the player.
+    pub fn warn_before(&self) -> Duration {
+        self.warn_before
+    }
+}
+
+fn hours_message(weekday: Weekday, ranges: &[(NaiveTime, NaiveTime)]) -> String {
+    if ranges.is_empty() {
+        return format!("No games on {}", weekday_name(weekday));
+    }
+    let ranges: Vec<String> = ranges
+        .iter()
+        .map(|(start, end)| format!("{}–{}", start.format("%H:%M"), end.format("%H:%M")))
+        .collect();
+    format!(
+        "Games are allowed {} on {}",
+        ranges.join(", "),
+        weekday_name(weekday)
+    )
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Mondays",
+        Weekday::Tue => "Tuesdays",
+        Weekday::Wed => "Wednesdays",
+        Weekday::Thu => "Thursdays",
+        Weekday::Fri => "Fridays",
+        Weekday::Sat => "Saturdays",
+        Weekday::Sun => "Sundays",
+    }
+}
+
+/// Parse a window like `15:00-19:00`.
+fn parse_range(range: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let invalid = || format!("invalid hours {}, expected e.g. 15:00-19:00", range);
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+    if start >= end {
+        return Err(format!("hours {} must end after they start", range));
+    }
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn limits(toml: &str) -> Result<Limits, String> {
+        toml::from_str(toml).map_err(|e| e.message().to_string())
+    }
+
+    /// A time on Saturday, 17 October 2026.
+    fn saturday(hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 10, 17, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn no_limits_allow_anything() {
+        let limits = Limits::default();
+        assert_eq!(
+            limits.allowance(saturday(3, 0), minutes(600)),
+            Allowance::Allowed(None)
+        );
+    }
+
+    #[test]
+    fn windows_allow_play_until_they_close() {
+        let limits = limits(r#"hours = { sat = ["09:00-10:00", "15:00-19:00"] }"#).unwrap();
+        let played = Duration::ZERO;
+        assert_eq!(
+            limits.allowance(saturday(9, 30), played),
+            Allowance::Allowed(Some(minutes(30)))
+        );
+        assert_eq!(
+            limits.allowance(saturday(15, 0), played),
+            Allowance::Allowed(Some(minutes(4 * 60)))
+        );
+        for outside in [saturday(8, 59), saturday(12, 0), saturday(19, 0)] {
+            assert_eq!(
+                limits.allowance(outside, played),
+                Allowance::Denied(
+                    "Games are allowed 09:00–10:00, 15:00–19:00 on Saturdays".to_string()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn days_not_listed_are_unrestricted() {
+        let limits = limits(r#"hours = { sunday = ["15:00-16:00"] }"#).unwrap();
+        assert_eq!(
+            limits.allowance(saturday(22, 0), Duration::ZERO),
+            Allowance::Allowed(None)
+        );
+    }
+
+    #[test]
+    fn empty_day_means_no_games() {
+        let limits = limits("hours = { sat = [] }").unwrap();
+        assert_eq!(
+            limits.allowance(saturday(12, 0), Duration::ZERO),
+            Allowance::Denied("No games on Saturdays".to_string())
+        );
+    }
+
+    #[test]
+    fn daily_allowance_runs_out() {
+        let limits = limits("daily_minutes = 60").unwrap();
+        assert_eq!(
+            limits.allowance(saturday(12, 0), minutes(45)),
+            Allowance::Allowed(Some(minutes(15)))
+        );
+        for played in [minutes(60), minutes(90)] {
+            assert_eq!(
+                limits.allowance(saturday(12, 0), played),
+                Allowance::Denied("That's all the games for today!".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn allowance_ends_with_the_window_or_the_daily_time_whichever_is_first() {
+        let limits = limits(
+            r#"daily_minutes = 60
+hours = { sat = ["15:00-19:00"] }"#,
+        )
+        .unwrap();
+        // 30 minutes of allowance left, with hours to go in the window
+        assert_eq!(
+            limits.allowance(saturday(16, 0), minutes(30)),
+            Allowance::Allowed(Some(minutes(30)))
+        );
+        // The window closes in 20 minutes
+        assert_eq!(
+            limits.allowance(saturday(18, 40), minutes(30)),
+            Allowance::Allowed(Some(minutes(20)))
+        );
+    }
+
+    #[test]
+    fn warning_defaults_to_five_minutes() {
+        assert_eq!(limits("").unwrap().warn_before(), minutes(5));
+        assert_eq!(
+            limits("warn_minutes = 2").unwrap().warn_before(),
+            minutes(2)
+        );
+    }
+
+    #[test]
+    fn invalid_hours_are_rejected() {
+        for (toml, error) in [
+            (
+                r#"hours = { sat = ["19:00-15:00"] }"#,
+                "hours 19:00-15:00 must end after they start",
+            ),
+            (
+                r#"hours = { sat = ["15:00-15:00"] }"#,
+                "hours 15:00-15:00 must end after they start",
+            ),
+            (
+                r#"hours = { sat = ["3pm-5pm"] }"#,
+                "invalid hours 3pm-5pm, expected e.g. 15:00-19:00",
+            ),
+            (
+                r#"hours = { sat = ["15:00"] }"#,
+                "invalid hours 15:00, expected e.g. 15:00-19:00",
+            ),
+            (
+                r#"hours = { funday = ["15:00-19:00"] }"#,
+                "unknown weekday funday",
+            ),
+        ] {
+            assert_eq!(limits(toml).unwrap_err(), error, "{}", toml);
+        }
+    }
+}
@@ -1,54 +1,89 @@
 mod app;
 mod assets;
 mod config;
-mod rfid_reader;
+mod config_watcher;
+mod crash_loop;
+mod devices;
+mod event_loop;
+mod history;
+mod launch_log;
+mod limits;
+mod process_tree;
+mod readers;
+mod retroarch;
 mod rom_preview;
 mod ui;
 
 use crate::app::BarelyGameConsole;
-use crate::config::{CardInfo, Config};
-use crate::rfid_reader::RFIDReader;
+use crate::config::{CardInfo, Config, Content, LaunchTarget, DEFAULT_CONFIG_PATH};
+use crate::config_watcher::ConfigWatcher;
+use crate::event_loop::{Action, Event, EventLoop, LaunchFailure, RunningGame};
+use crate::history::{format_play_time, History, Launch};
+use crate::launch_log::{file_safe, LaunchLog};
+use crate::limits::Allowance;
+use chrono::Local;
 use eframe::egui;
-use evdev::{Device, InputEventKind, Key};
-use nix::sys::signal::{kill, Signal};
-use nix::unistd::Pid;
+use evdev::{InputEventKind, Key};
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::{getuid, Pid};
+use std::collections::BTreeMap;
+use std::fs::{self, DirBuilder};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
-/// Shared state between the main loop, device listener threads, and the UI.
+/// Shared state between the main loop, the event loop, device listener threads,
+/// and the UI. What to do about taps, presses and exits is decided by the
+/// event loop alone; this only carries its decisions to the UI and game threads.
 ///
 /// The main loop alternates between running eframe (the launcher UI) and running
 /// a game process. When a game launches, we spawn it first (so Cage has a Wayland
 /// client), then close eframe after a delay. This ensures Cage always has at least
 /// one surface and doesn't exit. With eframe's surface destroyed during gameplay,
-/// Cage only services RetroArch — eliminating the frame callback deadlock.
+/// Cage only services RetroArch — eliminating the frame callback deadlock. Once
+/// the game exits, the event loop tells the main loop to bring eframe back.
 struct SharedState {
+    /// The active card table. Swapped wholesale when config.toml is reloaded.
+    config: RwLock<Arc<Config>>,
     /// The current UI app instance (None when eframe isn't running).
     ui_app: Mutex<Option<BarelyGameConsole>>,
-    /// Set by the power button thread to signal a game launch.
-    pending_launch: Mutex<Option<CardInfo>>,
-    /// The currently-previewed ROM (set by RFID, consumed by power button).
-    selected_rom: Mutex<Option<CardInfo>>,
-    /// PID of the running game process (set by game thread, read by power button for kill).
-    game_pid: Mutex<Option<u32>>,
-    /// True while a game thread is running (including after child exits, until main loop resets).
-    game_active: AtomicBool,
-    /// Timer version for debouncing card preview timeouts.
-    timer_version: AtomicUsize,
+    /// A failure to show once eframe is back, if it came in while eframe was closed.
+    launch_failure: Mutex<Option<LaunchFailure>>,
+    /// Feeds the event loop.
+    events: Sender<Event>,
 }
 
 impl SharedState {
-    fn new() -> Self {
+    fn new(config: Config, events: Sender<Event>) -> Self {
         Self {
+            config: RwLock::new(Arc::new(config)),
             ui_app: Mutex::new(None),
-            pending_launch: Mutex::new(None),
-            selected_rom: Mutex::new(None),
-            game_pid: Mutex::new(None),
-            game_active: AtomicBool::new(false),
-            timer_version: AtomicUsize::new(0),
+            launch_failure: Mutex::new(None),
+            events,
+        }
+    }
+
+    /// Snapshot the current config. Callers keep a consistent view even if a
+    /// reload lands while they're using it.
+    fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+
+    fn set_config(&self, config: Config) {
+        *self.config.write().unwrap() = Arc::new(config);
+    }
+
+    fn show_message(&self, message: &str) {
+        if let Ok(mut app) = self.ui_app.lock() {
+            if let Some(app) = app.as_mut() {
+                app.show_message(message.to_string());
+            }
         }
     }
 
@@ -59,53 +94,75 @@ impl SharedState {
             }
         }
     }
+
+    fn show_card(&self, rom: Option<String>, countdown: Option<Duration>) {
+        if let Ok(mut app) = self.ui_app.lock() {
+            if let Some(app) = app.as_mut() {
+                app.show_card(rom, countdown);
+            }
+        }
+    }
+
+    /// Show a failure now, or once the menu is back if eframe is closed or
+    /// closing for a launch.
+    fn show_failure(&self, failure: LaunchFailure) {
+        // launch_failure is picked up while holding ui_app, so it can't be missed
+        if let Ok(mut app) = self.ui_app.lock() {
+            match app.as_mut() {
+                Some(app) if !app.is_closing() => {
+                    app.show_failure(failure.card.artwork.clone(), failure.message())
+                }
+                _ => *self.launch_failure.lock().unwrap() = Some(failure),
+            }
+        }
+    }
+
+    /// Show the failure that came in while the menu was away, if any.
+    fn show_pending_failure(&self, app: &mut BarelyGameConsole) {
+        if let Some(failure) = self.launch_failure.lock().unwrap().take() {
+            app.show_failure(failure.card.artwork.clone(), failure.message());
+        }
+    }
+
+    /// Close eframe once `delay` is up, handing the screen over to a game.
+    fn close_ui_after(&self, delay: Duration) {
+        if let Some(app) = self.ui_app.lock().unwrap().as_mut() {
+            app.close_after(delay);
+            eprintln!("[surface] game spawned, closing eframe in {:.0?}", delay);
+        }
+    }
+
+    /// Bring the menu back after a game. If eframe hasn't closed yet (the game
+    /// quit right away) it just stays open; otherwise the main loop restarts it.
+    fn show_menu(&self, restart: &Sender<()>) {
+        let mut app = self.ui_app.lock().unwrap();
+        if app.as_mut().is_some_and(|app| app.cancel_close()) {
+            eprintln!("[surface] game exited before eframe closed, staying open");
+            self.send(Event::MenuShown);
+        } else {
+            // The main loop is waiting for this, having seen eframe close for a game
+            let _ = restart.send(());
+        }
+    }
+
+    fn send(&self, event: Event) {
+        // The event loop lives as long as the process
+        let _ = self.events.send(event);
+    }
 }
 
-/// The eframe App wrapper. Delegates rendering to BarelyGameConsole.
-///
-/// When a game launch is pending, it spawns the game process first (so Cage
-/// keeps a Wayland client), waits for RetroArch to create its surface, then
-/// closes the eframe window.
+/// A game exiting unsuccessfully within this long of launch counts as a failed
+/// launch rather than a finished game.
+static IMMEDIATE_EXIT: Duration = Duration::from_secs(5);
+
+/// The eframe App wrapper. Delegates rendering to BarelyGameConsole, which also
+/// closes the window when a game takes over the screen.
 struct Launcher {
     shared: Arc<SharedState>,
-    /// When set, close the window after this deadline (gives RetroArch time to map its surface).
-    close_after: Option<Instant>,
 }
 
 impl eframe::App for Launcher {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Phase 2: deadline reached — close the window
-        if let Some(deadline) = self.close_after {
-            if Instant::now() >= deadline {
-                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                return;
-            }
-            // Keep ticking until the deadline
-            ctx.request_repaint();
-            return;
-        }
-
-        // Phase 1: pending launch detected — spawn the game, schedule delayed close
-        let should_launch = self
-            .shared
-            .pending_launch
-            .lock()
-            .ok()
-            .map_or(false, |p| p.is_some());
-
-        if should_launch {
-            let card = self.shared.pending_launch.lock().unwrap().clone().unwrap();
-            self.shared.game_active.store(true, Ordering::SeqCst);
-            let shared = Arc::clone(&self.shared);
-            thread::spawn(move || run_game(&card, &shared));
-            // Give RetroArch 1 second to create its Wayland surface before we close ours
-            self.close_after = Some(Instant::now() + Duration::from_secs(1));
-            eprintln!("[surface] game spawned, closing eframe in 1s");
-            ctx.request_repaint();
-            return;
-        }
-
-        // Normal UI rendering
         if let Ok(mut app) = self.shared.ui_app.lock() {
             if let Some(app) = app.as_mut() {
                 app.update(ctx);
@@ -114,17 +171,85 @@ impl eframe::App for Launcher {
     }
 }
 
+/// Command-line options.
+struct Args {
+    /// Validate the config and exit instead of starting the UI.
+    check_config: bool,
+    /// Print play time totals and exit instead of starting the UI.
+    history: bool,
+    /// From `--config`, then `BGC_CONFIG`, then `config.toml` in the working directory.
+    config_path: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut check_config = false;
+    let mut history = false;
+    let mut config_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--check-config" {
+            check_config = true;
+        } else if arg == "--history" {
+            history = true;
+        } else if arg == "--config" {
+            config_path = Some(args.next().ok_or("--config requires a path")?);
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            config_path = Some(path.to_string());
+        } else {
+            return Err(format!("unknown argument {}", arg));
+        }
+    }
+    let config_path = config_path
+        .or_else(|| std::env::var("BGC_CONFIG").ok())
+        .unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+    Ok(Args {
+        check_config,
+        history,
+        config_path: PathBuf::from(config_path),
+    })
+}
+
 fn main() -> Result<(), eframe::Error> {
-    let config = Config::load();
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("usage: barely-game-console [--config <path>] [--check-config | --history]");
+        std::process::exit(2);
+    });
+    if args.check_config {
+        std::process::exit(check_config(&args.config_path));
+    }
+    if args.history {
+        std::process::exit(print_history(&args.config_path));
+    }
+
+    // A broken fragment or card shouldn't keep the console from booting
+    let (config, skipped) = Config::load_partial(&args.config_path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    for problem in skipped {
+        eprintln!("[config] skipped: {}", problem);
+    }
+    for problem in config.check() {
+        eprintln!("[config] warning: {}", problem);
+    }
     eprintln!(
         "barely-game-console started, {} cards configured",
         config.rfid_cards.len()
     );
 
-    let shared = Arc::new(SharedState::new());
+    let (events, receiver) = mpsc::channel();
+    let (restart, restarts) = mpsc::channel();
+    let shared = Arc::new(SharedState::new(config, events));
+    process_tree::become_subreaper();
 
     // Start device listeners once — they persist across eframe restarts
-    device_listener(config, Arc::clone(&shared));
+    thread::spawn({
+        let shared = Arc::clone(&shared);
+        move || run_event_loop(receiver, &restart, &shared)
+    });
+    device_listener(Arc::clone(&shared));
+    config_watcher(args.config_path, Arc::clone(&shared));
 
     loop {
         // Clear stale textures from the previous eframe instance
@@ -136,29 +261,30 @@ fn main() -> Result<(), eframe::Error> {
             Box::new({
                 let shared = Arc::clone(&shared);
                 move |cc| {
-                    let app = BarelyGameConsole::new(cc);
-                    *shared.ui_app.lock().unwrap() = Some(app);
+                    let mut app = BarelyGameConsole::new(cc);
+                    let mut ui_app = shared.ui_app.lock().unwrap();
+                    shared.show_pending_failure(&mut app);
+                    *ui_app = Some(app);
+                    drop(ui_app);
+                    shared.send(Event::MenuShown);
                     Ok(Box::new(Launcher {
                         shared: Arc::clone(&shared),
-                        close_after: None,
                     }))
                 }
             }),
         )?;
 
         // eframe exited — clear the stale UI app reference
-        *shared.ui_app.lock().unwrap() = None;
+        let app = shared.ui_app.lock().unwrap().take();
         eprintln!("[surface] eframe exited");
 
-        if shared.game_active.load(Ordering::SeqCst) {
-            // Game was spawned before eframe closed — wait for it to finish
+        if app.is_some_and(|app| app.is_closing()) {
+            // eframe closed to hand over to a game; the event loop says when
+            // the menu is wanted back (perhaps already)
             eprintln!("[surface] waiting for game to exit");
-            while shared.game_active.load(Ordering::SeqCst) {
-                thread::sleep(Duration::from_millis(100));
+            if restarts.recv().is_err() {
+                break;
             }
-            // Clear the consumed pending_launch
-            shared.pending_launch.lock().unwrap().take();
-            // Game exited — loop back to restart eframe
         } else {
             // Window closed without a game launch (shouldn't happen in kiosk mode)
             break;
@@ -168,89 +294,380 @@ fn main() -> Result<(), eframe::Error> {
     Ok(())
 }
 
-fn build_game_command(card: &CardInfo) -> (String, Command) {
-    if let Some(command) = &card.command {
-        let desc = command.join(" ");
-        let mut iter = command.iter();
-        let first = iter.next().expect("Empty command list");
-        let mut cmd = Command::new(first);
-        cmd.args(iter);
-        (desc, cmd)
+/// Validate the config without starting the UI. Prints every problem found and
+/// returns the process exit code, so CI and deploys can reject bad configs.
+fn check_config(path: &Path) -> i32 {
+    // Broken cards are left out rather than hiding what's wrong with the rest
+    let (config, mut problems) = match Config::load_partial(path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    problems.extend(config.check());
+    config.sort_problems(&mut problems);
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    if problems.is_empty() {
+        eprintln!("config ok, {} cards configured", config.rfid_cards.len());
+        0
     } else {
-        let emulator = card.emulator.as_ref().expect("Missing emulator");
-        let rom_path = card.rom_path.as_ref().expect("Missing rom path");
-        let desc = format!("retroarch -L {} {}", emulator, rom_path);
-        let mut cmd = Command::new("retroarch");
-        cmd.arg("-L").arg(emulator).arg(rom_path);
-        let config_path = std::env::var("BGC_RETROARCH_CONFIG")
-            .unwrap_or_else(|_| "retroarch.cfg".to_string());
-        cmd.arg("--appendconfig").arg(&config_path);
-        (desc, cmd)
+        eprintln!("{} problems found", problems.len());
+        1
+    }
+}
+
+/// Print total play time per card and per day from the history file.
+fn print_history(path: &Path) -> i32 {
+    let config = match Config::load(path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let launches = match History::new(config.launcher.history_path).launches() {
+        Ok(launches) => launches,
+        Err(e) => {
+            eprintln!("failed to read history: {}", e);
+            return 1;
+        }
+    };
+    println!("Per card:");
+    for (card, total) in &history::totals_per_card(&launches) {
+        println!("  {}  {}", card, format_play_time(*total));
+    }
+    println!("Per day:");
+    for (day, cards) in &history::totals_per_day(&launches) {
+        let total = cards.values().sum();
+        let breakdown: Vec<String> = cards
+            .iter()
+            .map(|(card, time)| format!("{} {}", card, format_play_time(*time)))
+            .collect();
+        println!(
+            "  {}  {}  ({})",
+            day,
+            format_play_time(total),
+            breakdown.join(", ")
+        );
+    }
+    0
+}
+
+fn build_game_command(card: &CardInfo) -> io::Result<(String, Command)> {
+    match &card.launch {
+        LaunchTarget::Command { command } => {
+            let desc = command.join(" ");
+            let mut cmd = Command::new(&command[0]);
+            cmd.args(&command[1..]);
+            Ok((desc, cmd))
+        }
+        LaunchTarget::RetroArch {
+            core,
+            content,
+            append_config,
+            args,
+            settings,
+            resume,
+        } => {
+            let rom_path = match content {
+                Content::Rom(rom_path) => rom_path.clone(),
+                Content::Discs(discs) => write_playlist(&card.id, discs)?,
+            };
+            let mut cmd = Command::new("retroarch");
+            cmd.arg("-L").arg(core).arg(&rom_path);
+            let config_path = std::env::var("BGC_RETROARCH_CONFIG")
+                .unwrap_or_else(|_| "retroarch.cfg".to_string());
+            let mut append_config: Vec<String> = std::iter::once(config_path)
+                .chain(append_config.iter().cloned())
+                .collect();
+            let mut settings = settings.clone();
+            let mut defaults = retroarch::LAUNCH_SETTINGS.to_vec();
+            if *resume {
+                defaults.extend(retroarch::RESUME_SETTINGS);
+            }
+            for (key, value) in defaults {
+                // Explicit card settings win over the launcher's defaults
+                settings
+                    .entry(key.to_string())
+                    .or_insert_with(|| value.to_string());
+            }
+            append_config.push(write_settings_cfg(&card.id, &settings)?);
+            // RetroArch takes multiple append-configs as a single `|`-separated list
+            cmd.arg("--appendconfig").arg(append_config.join("|"));
+            cmd.args(args);
+            let desc = format!("retroarch -L {} {} {}", core, rom_path, args.join(" "));
+            Ok((desc.trim_end().to_string(), cmd))
+        }
+    }
+}
+
+/// Write a multi-disc card's discs to a generated `.m3u` playlist. Entries are
+/// made absolute, since RetroArch resolves them relative to the playlist. It's
+/// kept in the state directory, since RetroArch may save beside its content.
+fn write_playlist(card_id: &str, discs: &[String]) -> io::Result<String> {
+    let path = state_dir()?.join(format!("bgc-{}.m3u", file_safe(card_id)));
+    let mut contents = String::new();
+    for disc in discs {
+        contents.push_str(&std::path::absolute(disc)?.to_string_lossy());
+        contents.push('\n');
     }
+    fs::write(&path, contents)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Write a card's inline RetroArch settings to a generated config file, so they
+/// can be layered on with `--appendconfig` like any other override.
+fn write_settings_cfg(card_id: &str, settings: &BTreeMap<String, String>) -> io::Result<String> {
+    let path = runtime_dir()?.join(format!("bgc-{}.cfg", file_safe(card_id)));
+    let mut contents = format!("# Generated by barely-game-console for card {}\n", card_id);
+    for (key, value) in settings {
+        contents.push_str(&format!("{} = \"{}\"\n", key, value));
+    }
+    fs::write(&path, contents)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Where generated settings for RetroArch go: `$XDG_RUNTIME_DIR/barely-game-console`,
+/// or a per-user directory in the temp dir.
+fn runtime_dir() -> io::Result<PathBuf> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir).join("barely-game-console"),
+        None => std::env::temp_dir().join(format!("barely-game-console-{}", getuid())),
+    };
+    private_dir(&dir)
+}
+
+/// Where generated files that must survive a reboot go, such as playlists that
+/// saves are named after: `$XDG_STATE_HOME/barely-game-console`, by default in
+/// `~/.local/state`.
+fn state_dir() -> io::Result<PathBuf> {
+    let state_home = match (std::env::var_os("XDG_STATE_HOME"), std::env::var_os("HOME")) {
+        (Some(state_home), _) => PathBuf::from(state_home),
+        (None, Some(home)) => PathBuf::from(home).join(".local/state"),
+        (None, None) => return Err(io::Error::other("neither XDG_STATE_HOME nor HOME is set")),
+    };
+    fs::create_dir_all(&state_home)?;
+    private_dir(&state_home.join("barely-game-console"))
+}
+
+/// Create `dir` if needed and make sure nobody else may own or write to it, so
+/// a generated file can't be swapped out or redirected through a symlink.
+fn private_dir(dir: &Path) -> io::Result<PathBuf> {
+    let uid = getuid();
+    match DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    // Not following symlinks, so one planted in place of the directory is refused
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid.as_raw() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::other(format!(
+            "{} isn't a directory private to this user",
+            dir.display()
+        )));
+    }
+    Ok(dir.to_path_buf())
+}
+
+/// Check the play-time limits against today's history.
+fn play_allowance(shared: &SharedState) -> Allowance {
+    let config = shared.config();
+    let now = Local::now();
+    let launches = History::new(config.launcher.history_path.clone())
+        .launches()
+        .unwrap_or_else(|e| {
+            eprintln!("[limits] failed to read history: {}", e);
+            Vec::new()
+        });
+    let played_today = history::totals_per_day(&launches)
+        .get(&now.date_naive())
+        .map(|cards| cards.values().sum())
+        .unwrap_or_default();
+    config.limits.allowance(now, played_today)
 }
 
-/// Spawn and wait for a game process. Runs in a dedicated thread so the main
-/// thread can close eframe after a delay (ensuring Cage always has a client).
-fn run_game(card: &CardInfo, shared: &SharedState) {
-    let (cmd_desc, mut cmd) = build_game_command(card);
+/// Spawn and wait for a game process, allowed `time_left` of play if limited.
+/// Runs in a dedicated thread so eframe can close after a delay (ensuring Cage
+/// always has a client). Always ends with `GameExited`.
+fn run_game(card: &CardInfo, time_left: Option<Duration>, shared: &SharedState) {
+    let (cmd_desc, mut cmd) = match build_game_command(card) {
+        Ok(built) => built,
+        Err(e) => {
+            eprintln!("[launch] failed to prepare card={}: {}", card.id, e);
+            shared.send(Event::GameExited {
+                card: card.clone(),
+                failure: Some(e.to_string()),
+                log: None,
+            });
+            return;
+        }
+    };
 
     if let Some(dir) = &card.working_dir {
         cmd.current_dir(dir);
     }
+    if card.env_clear {
+        cmd.env_clear();
+        for key in &card.env_inherit {
+            if let Some(value) = std::env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+    }
+    cmd.envs(&card.env);
 
     eprintln!("[launch] {}", cmd_desc);
     cmd.stdin(Stdio::null());
+    // Lead a new process group, so stopping the game reaches its helpers too
+    cmd.process_group(0);
+    let log = open_launch_log(card, &cmd_desc, &mut cmd, shared);
+    let log_path = log.as_ref().map(|log| log.path().to_path_buf());
+    let launched_at = Local::now();
 
-    match cmd.spawn() {
+    let mut failure = None;
+    let launch = match cmd.spawn() {
         Ok(mut child) => {
             let child_pid = child.id();
             eprintln!("[launch] spawned pid={}", child_pid);
-            *shared.game_pid.lock().unwrap() = Some(child_pid);
+            let game = RunningGame {
+                pid: child_pid,
+                card: card.clone(),
+            };
+            shared.send(Event::GameStarted { game, time_left });
             let started = Instant::now();
             let status = child.wait();
             let elapsed = started.elapsed();
-            match status {
-                Ok(s) => eprintln!(
-                    "[exit] pid={} status={} after {:.0?}",
-                    child_pid, s, elapsed
-                ),
-                Err(e) => eprintln!(
-                    "[exit] pid={} wait error: {} after {:.0?}",
-                    child_pid, e, elapsed
-                ),
+            let outcome = match &status {
+                Ok(s) => format!("status={}", s),
+                Err(e) => format!("wait error: {}", e),
+            };
+            eprintln!("[exit] pid={} {} after {:.0?}", child_pid, outcome, elapsed);
+            if let Ok(status) = &status {
+                if !status.success() && elapsed < IMMEDIATE_EXIT {
+                    failure = Some(format!("it quit right away ({})", status));
+                }
+            }
+            if let Some(log) = log {
+                log.finish(&outcome, elapsed);
+            }
+            // Don't return to the menu while anything from the game is still running
+            process_tree::cleanup(Pid::from_raw(child_pid as i32));
+            Launch {
+                card: card.id.clone(),
+                started: launched_at,
+                duration_secs: elapsed.as_secs_f64(),
+                exit_code: status.as_ref().ok().and_then(|s| s.code()),
+                signal: status.as_ref().ok().and_then(|s| s.signal()),
+                error: status.err().map(|e| e.to_string()),
             }
-            *shared.game_pid.lock().unwrap() = None;
         }
         Err(e) => {
             eprintln!("[launch] failed to spawn: {}", e);
+            failure = Some(e.to_string());
+            if let Some(log) = log {
+                log.finish(&format!("failed to spawn: {}", e), Duration::ZERO);
+            }
+            Launch {
+                card: card.id.clone(),
+                started: launched_at,
+                duration_secs: 0.0,
+                exit_code: None,
+                signal: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    let history = History::new(shared.config().launcher.history_path.clone());
+    if let Err(e) = history.record(&launch) {
+        eprintln!("[history] failed to record launch: {}", e);
+    }
+    shared.send(Event::GameExited {
+        card: card.clone(),
+        failure,
+        log: log_path,
+    });
+}
+
+/// Point the command's stdout and stderr at a fresh per-launch log file. If the
+/// log can't be created, output stays on the launcher's stderr.
+fn open_launch_log(
+    card: &CardInfo,
+    cmd_desc: &str,
+    cmd: &mut Command,
+    shared: &SharedState,
+) -> Option<LaunchLog> {
+    let config = shared.config();
+    let settings = &config.launcher;
+    let log = LaunchLog::create(&settings.log_dir, &card.id, cmd_desc, settings.log_keep)
+        .and_then(|log| Ok((log.stdio()?, log)));
+    match log {
+        Ok(((stdout, stderr), log)) => {
+            eprintln!("[launch] logging to {}", log.path().display());
+            cmd.stdout(stdout).stderr(stderr);
+            Some(log)
         }
+        Err(e) => {
+            eprintln!("[launch] failed to create launch log: {}", e);
+            None
+        }
+    }
+}
+
+/// Ask a running game to quit with SIGTERM to its process group, which
+/// RetroArch handles like its own quit (saving SRAM and state). A RetroArch
+/// listening for network commands is sent `QUIT` first, though nothing tells
+/// whether it arrived. Killing the game if it doesn't quit is up to the event loop.
+fn stop_game(game: &RunningGame) {
+    let pgid = Pid::from_raw(game.pid as i32);
+    if let Some(port) = retroarch::network_cmd_port(&game.card) {
+        eprintln!("[stop] sending QUIT to pid={}", game.pid);
+        if let Err(e) = retroarch::send_command(port, "QUIT") {
+            eprintln!("[stop] failed to send QUIT: {}", e);
+        }
+    }
+    eprintln!("[stop] sending SIGTERM to pgid={}", pgid);
+    if let Err(e) = killpg(pgid, Signal::SIGTERM) {
+        eprintln!("[stop] SIGTERM failed: {}", e);
     }
-    shared.game_active.store(false, Ordering::SeqCst);
 }
 
-fn device_listener(config: Config, shared: Arc<SharedState>) {
+/// Show a message on the game's screen, if it's RetroArch listening for commands.
+fn warn_player(game: &RunningGame, message: &str) {
+    eprintln!("[limits] warning pid={}: {}", game.pid, message);
+    match retroarch::network_cmd_port(&game.card) {
+        Some(port) => {
+            if let Err(e) = retroarch::show_message(port, message) {
+                eprintln!("[limits] failed to show warning: {}", e);
+            }
+        }
+        None => eprintln!(
+            "[limits] card={} can't show on-screen warnings",
+            game.card.id
+        ),
+    }
+}
+
+fn config_watcher(path: PathBuf, shared: Arc<SharedState>) {
+    thread::spawn(move || {
+        ConfigWatcher::new(path).run(|config| {
+            shared.set_config(config);
+            shared.send(Event::ConfigReloaded);
+        });
+    });
+}
+
+fn device_listener(shared: Arc<SharedState>) {
     // Power button listener
     thread::spawn({
         let shared = Arc::clone(&shared);
         move || loop {
-            let device_path = match rfid_reader::find_device_path_by_name("Power Button") {
-                Some(path) => path,
-                None => {
-                    eprintln!("Power Button device not found, retrying...");
-                    thread::sleep(Duration::from_secs(1));
-                    continue;
-                }
-            };
-            let mut device = match Device::open(&device_path) {
-                Ok(d) => d,
-                Err(e) => {
-                    eprintln!("Failed to open Power Button: {}, retrying...", e);
-                    thread::sleep(Duration::from_secs(1));
-                    continue;
-                }
-            };
+            let matcher = shared.config().devices.power_button.clone();
+            let (device_path, mut device) = devices::wait_for_device(&matcher);
             let _ = device.grab();
-            eprintln!("Power button ready on {}", device_path);
+            eprintln!("Power button ready on {}", device_path.display());
 
             'read: loop {
                 let events = match device.fetch_events() {
@@ -262,64 +679,91 @@ fn device_listener(config: Config, shared: Arc<SharedState>) {
                 };
                 for event in events {
                     if event.value() == 0 && event.kind() == InputEventKind::Key(Key::KEY_POWER) {
-                        // Snapshot game_pid with a brief lock, then release
-                        let game_pid_val = shared.game_pid.lock().ok().and_then(|p| *p);
-
-                        if let Some(pid) = game_pid_val {
-                            // Game is running — kill it
-                            eprintln!("[power] killing game pid={}", pid);
-                            let _ = kill(Pid::from_raw(pid as i32), Signal::SIGKILL);
-                        } else {
-                            // No game running — launch if a ROM is selected
-                            let rom = shared.selected_rom.lock().ok().and_then(|mut s| s.take());
-                            if let Some(rom) = rom {
-                                shared.timer_version.fetch_add(1, Ordering::SeqCst);
-                                // Signal the main loop: store the card and trigger eframe close
-                                *shared.pending_launch.lock().unwrap() = Some(rom);
-                                shared.enqueue_rom(None);
-                            }
-                        }
+                        shared.send(Event::PowerButton);
                     }
                 }
             }
         }
     });
 
-    // RFID listener
-    thread::spawn({
+    // RFID listeners, one per configured reader
+    for reader in shared.config().devices.rfid_readers.clone() {
         let shared = Arc::clone(&shared);
-        move || {
-            let reader = RFIDReader::new();
-            reader.run(move |id| {
-                let game_running = shared
-                    .game_pid
-                    .lock()
-                    .ok()
-                    .map_or(false, |p| p.is_some());
-
-                if !game_running {
-                    if let Some(rom) = config.rfid_cards.get(&id) {
-                        eprintln!("[rfid] card={} artwork={}", id, rom.artwork);
-                        let current_version = {
-                            let mut sel_rom = shared.selected_rom.lock().unwrap();
-                            *sel_rom = Some(rom.clone());
-                            shared.timer_version.fetch_add(1, Ordering::SeqCst) + 1
-                        };
-                        shared.enqueue_rom(Some(rom.artwork.clone()));
-
-                        let shared = Arc::clone(&shared);
-                        thread::spawn(move || {
-                            thread::sleep(Duration::from_secs(5));
-                            if shared.timer_version.load(Ordering::SeqCst) == current_version {
-                                *shared.selected_rom.lock().unwrap() = None;
-                                shared.enqueue_rom(None);
-                            }
-                        });
-                    } else {
-                        eprintln!("[rfid] unknown card={}", id);
-                    }
+        thread::spawn(move || {
+            eprintln!("[rfid] starting {} reader", reader);
+            reader
+                .open()
+                .run(&mut |event| shared.send(Event::Reader(event)));
+        });
+    }
+}
+
+/// Feed every event to the state machine and carry out what it decides. A
+/// `Timer` event is made up whenever the state machine's deadline passes.
+/// `restart` asks the main loop to bring eframe back after a game.
+fn run_event_loop(events: Receiver<Event>, restart: &Sender<()>, shared: &Arc<SharedState>) {
+    let mut state = EventLoop::new();
+    loop {
+        let event = match state.deadline() {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match events.recv_timeout(timeout) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => Event::Timer,
+                    Err(RecvTimeoutError::Disconnected) => return,
                 }
-            });
+            }
+            None => match events.recv() {
+                Ok(event) => event,
+                Err(_) => return,
+            },
+        };
+        for action in state.handle(event, &shared.config(), Instant::now()) {
+            perform(action, restart, shared);
         }
+    }
+}
+
+fn perform(action: Action, restart: &Sender<()>, shared: &Arc<SharedState>) {
+    match action {
+        Action::ShowCard { artwork, countdown } => shared.show_card(Some(artwork), countdown),
+        Action::ClearPreview => shared.enqueue_rom(None),
+        Action::ShowFailure(failure) => shared.show_failure(failure),
+        Action::Launch(card) => launch(card, shared),
+        Action::ShowMenu => shared.show_menu(restart),
+        Action::Warn { game, message } => warn_player(&game, &message),
+        Action::Stop(game) => stop_game(&game),
+        Action::Kill(game) => {
+            let _ = killpg(Pid::from_raw(game.pid as i32), Signal::SIGKILL);
+        }
+    }
+}
+
+/// Launch a card unless play time or missing files say otherwise, in which case
+/// the event loop hears `LaunchRefused`.
+fn launch(card: CardInfo, shared: &Arc<SharedState>) {
+    let time_left = match play_allowance(shared) {
+        Allowance::Allowed(time_left) => time_left,
+        Allowance::Denied(reason) => {
+            eprintln!("[limits] refusing card={}: {}", card.id, reason);
+            shared.show_message(&reason);
+            shared.enqueue_rom(None);
+            shared.send(Event::LaunchRefused);
+            return;
+        }
+    };
+    if let Some(reason) = card.missing_content() {
+        eprintln!("[launch] refusing card={}: {}", card.id, reason);
+        shared.show_failure(LaunchFailure { card, reason });
+        shared.send(Event::LaunchRefused);
+        return;
+    }
+    shared.enqueue_rom(None);
+    // Spawn the game first, then give it a second to create its Wayland
+    // surface before we close ours
+    thread::spawn({
+        let shared = Arc::clone(shared);
+        move || run_game(&card, time_left, &shared)
     });
+    shared.close_ui_after(Duration::from_secs(1));
 }
@@ -0,0 +1,102 @@
+use nix::sys::prctl;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{getpid, Pid};
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long leftovers get to exit after each signal.
+static CLEANUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Make the launcher a child subreaper, so processes that escape a game's
+/// process group (e.g. a wrapper script's daemon calling setsid) are reparented
+/// to us instead of init, where `cleanup` can still find them.
+pub fn become_subreaper() {
+    if let Err(e) = prctl::set_child_subreaper(true) {
+        eprintln!("[reap] failed to become child subreaper: {}", e);
+    }
+}
+
+/// Terminate everything a game left behind once its group leader `pgid` has
+/// exited: the rest of its process group and any orphans reparented to the
+/// launcher. Returns once nothing survives, logging anything that won't die.
+pub fn cleanup(pgid: Pid) {
+    reap();
+    let leftovers = survivors(pgid);
+    if leftovers.is_empty() {
+        return;
+    }
+    for signal in [Signal::SIGTERM, Signal::SIGKILL] {
+        let leftovers = survivors(pgid);
+        eprintln!(
+            "[reap] {} left behind by pgid={}: {:?}, sending {}",
+            leftovers.len(),
+            pgid,
+            leftovers,
+            signal
+        );
+        for pid in leftovers {
+            let _ = kill(pid, signal);
+        }
+        if wait_until_gone(pgid, CLEANUP_TIMEOUT) {
+            eprintln!("[reap] pgid={} fully exited", pgid);
+            return;
+        }
+    }
+    eprintln!("[reap] processes survived SIGKILL: {:?}", survivors(pgid));
+}
+
+fn wait_until_gone(pgid: Pid, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        reap();
+        if survivors(pgid).is_empty() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Reap any exited children. Orphans reparented to the launcher become our
+/// zombies when they die.
+fn reap() {
+    while let Ok(status) = waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+        if status == WaitStatus::StillAlive {
+            break;
+        }
+    }
+}
+
+/// Live processes from a game's session: members of its process group, plus
+/// any children of the launcher (games are the only thing it spawns, so any
+/// child left after the game exits is an orphan from it).
+fn survivors(pgid: Pid) -> Vec<Pid> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let me = getpid();
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .map(Pid::from_raw)
+        .filter(|&pid| match stat(pid) {
+            Some((state, ppid, pgrp)) => state != 'Z' && (pgrp == pgid || ppid == me),
+            None => false,
+        })
+        .collect()
+}
+
+/// State, parent PID and process group from `/proc/<pid>/stat`.
+fn stat(pid: Pid) -> Option<(char, Pid, Pid)> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name is parenthesized and may itself contain spaces or parens
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let ppid = fields.next()?.parse().ok()?;
+    let pgrp = fields.next()?.parse().ok()?;
+    Some((state, Pid::from_raw(ppid), Pid::from_raw(pgrp)))
+}
@@ -0,0 +1,52 @@
+use super::wedge::{WedgeDecoder, WedgeSettings};
+use super::{Reader, ReaderEvent};
+use crate::devices::{self, DeviceMatch};
+use evdev::InputEventKind;
+
+/// A keyboard-wedge reader: a USB HID device that types each card ID.
+pub struct EvdevReader {
+    device: DeviceMatch,
+    decoder: WedgeDecoder,
+}
+
+impl EvdevReader {
+    pub fn new(device: DeviceMatch, decoder: WedgeSettings) -> Self {
+        Self {
+            device,
+            decoder: WedgeDecoder::new(decoder),
+        }
+    }
+}
+
+impl Reader for EvdevReader {
+    fn run(&mut self, on_event: &mut dyn FnMut(ReaderEvent)) {
+        loop {
+            let (device_path, mut device) = devices::wait_for_device(&self.device);
+            let _ = device.grab();
+            eprintln!("RFID reader ready on {}", device_path.display());
+
+            loop {
+                match device.fetch_events() {
+                    Ok(events) => {
+                        for event in events {
+                            if let InputEventKind::Key(key) = event.kind() {
+                                match self.decoder.key(key, event.value(), event.timestamp()) {
+                                    Some(Ok(id)) => on_event(ReaderEvent::Tapped(id)),
+                                    Some(Err(reason)) => {
+                                        eprintln!("[rfid] rejected read: {}", reason)
+                                    }
+                                    None => {}
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("RFID reader error: {}, re-opening device...", e);
+                        self.decoder.reset();
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
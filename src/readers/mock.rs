@@ -0,0 +1,102 @@
+use super::{Reader, ReaderEvent};
+use nix::sys::stat::Mode;
+use nix::unistd;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+static RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Reads card IDs, one per line, from a FIFO — e.g.
+/// `echo 0001234567 > /tmp/bgc-cards` taps that card, and `-0001234567`
+/// takes it away again. The FIFO is created if it doesn't exist.
+pub struct MockReader {
+    path: PathBuf,
+}
+
+impl MockReader {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn session(&self, on_event: &mut dyn FnMut(ReaderEvent)) -> io::Result<()> {
+        // Blocks until something opens the FIFO for writing
+        let fifo = BufReader::new(File::open(&self.path)?);
+        for line in fifo.lines() {
+            let line = line?;
+            let line = line.trim();
+            if let Some(id) = line.strip_prefix('-') {
+                on_event(ReaderEvent::Removed(id.to_string()));
+            } else if !line.is_empty() {
+                on_event(ReaderEvent::Tapped(line.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Reader for MockReader {
+    fn run(&mut self, on_event: &mut dyn FnMut(ReaderEvent)) {
+        match fs::metadata(&self.path) {
+            Ok(meta) if meta.file_type().is_fifo() => {}
+            Ok(_) => {
+                eprintln!(
+                    "[mock] {} is not a FIFO, reader disabled",
+                    self.path.display()
+                );
+                return;
+            }
+            Err(_) => {
+                if let Err(e) = unistd::mkfifo(&self.path, Mode::from_bits_truncate(0o600)) {
+                    eprintln!(
+                        "[mock] failed to create {}: {}, reader disabled",
+                        self.path.display(),
+                        e
+                    );
+                    return;
+                }
+            }
+        }
+        eprintln!("Mock reader ready on {}", self.path.display());
+        loop {
+            // Each writer closing the FIFO ends a session; reopen for the next
+            if let Err(e) = self.session(on_event) {
+                eprintln!("[mock] {}: {}", self.path.display(), e);
+                thread::sleep(RETRY_DELAY);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_taps_and_removals_from_a_fifo() {
+        let path = std::env::temp_dir().join(format!("bgc-mock-test-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        unistd::mkfifo(&path, Mode::from_bits_truncate(0o600)).unwrap();
+        let writer = thread::spawn({
+            let path = path.clone();
+            move || fs::write(&path, "0001\n\n  0002 \n-0001\n").unwrap()
+        });
+
+        let mut events = Vec::new();
+        let result = MockReader::new(path.clone()).session(&mut |event| events.push(event));
+        writer.join().unwrap();
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert_eq!(
+            events,
+            [
+                ReaderEvent::Tapped("0001".to_string()),
+                ReaderEvent::Tapped("0002".to_string()),
+                ReaderEvent::Removed("0001".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,455 @@
+mod evdev;
+mod mock;
+mod ndef;
+mod pcsc;
+mod serial;
+mod wedge;
+
+use crate::devices::DeviceMatch;
+use serde::Deserialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+use wedge::{Case, Charset, Format, Terminator, WedgeSettings};
+
+/// A source of card events. `run` reads forever, recovering from device
+/// errors itself.
+pub trait Reader {
+    fn run(&mut self, on_event: &mut dyn FnMut(ReaderEvent));
+}
+
+/// Something that happened on a reader.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReaderEvent {
+    /// A card was presented. Readers that repeat reads while a card is present
+    /// may report the same card again.
+    Tapped(String),
+    /// A card was taken away. Only readers that can tell report this.
+    Removed(String),
+}
+
+/// One entry of `rfid_readers`: which backend reads cards and its settings.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "RawReaderConfig")]
+pub enum ReaderConfig {
+    /// A keyboard-wedge reader, read through evdev.
+    Evdev {
+        device: DeviceMatch,
+        decoder: WedgeSettings,
+    },
+    /// Any PC/SC reader, through the pcscd socket.
+    Pcsc {
+        /// Only use readers whose name contains this.
+        reader: Option<String>,
+        id_from: IdFrom,
+    },
+    /// A UART reader module.
+    Serial {
+        port: PathBuf,
+        protocol: SerialProtocol,
+        baud: u32,
+        id_from: IdFrom,
+    },
+    /// Card IDs written as lines to a FIFO, for trying things out without hardware.
+    Mock { path: PathBuf },
+}
+
+impl Default for ReaderConfig {
+    fn default() -> Self {
+        ReaderConfig::Evdev {
+            device: DeviceMatch::by_name("HID 413d:2107"),
+            decoder: WedgeSettings::default(),
+        }
+    }
+}
+
+impl ReaderConfig {
+    pub fn open(self) -> Box<dyn Reader> {
+        match self {
+            ReaderConfig::Evdev { device, decoder } => {
+                Box::new(evdev::EvdevReader::new(device, decoder))
+            }
+            ReaderConfig::Pcsc { reader, id_from } => {
+                Box::new(pcsc::PcscReader::new(reader, id_from))
+            }
+            ReaderConfig::Serial {
+                port,
+                protocol: SerialProtocol::Rdm6300,
+                baud,
+                ..
+            } => Box::new(serial::Rdm6300::new(port, baud)),
+            ReaderConfig::Serial {
+                port,
+                protocol: SerialProtocol::Pn532,
+                baud,
+                id_from,
+            } => Box::new(serial::Pn532::new(port, baud, id_from)),
+            ReaderConfig::Mock { path } => Box::new(mock::MockReader::new(path)),
+        }
+    }
+}
+
+impl fmt::Display for ReaderConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReaderConfig::Evdev { device, .. } => write!(f, "evdev {}", device),
+            ReaderConfig::Pcsc { reader, .. } => match reader {
+                Some(reader) => write!(f, "pcsc reader={:?}", reader),
+                None => write!(f, "pcsc"),
+            },
+            ReaderConfig::Serial { port, protocol, .. } => {
+                write!(f, "{} on {}", protocol, port.display())
+            }
+            ReaderConfig::Mock { path } => write!(f, "mock {}", path.display()),
+        }
+    }
+}
+
+/// Which part of an NFC tag is used as its card ID.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdFrom {
+    /// The tag's UID, in uppercase hex.
+    #[default]
+    Uid,
+    /// The first text or URI record of the tag's NDEF message, falling back to
+    /// the UID for tags without one.
+    Ndef,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialProtocol {
+    /// 125 kHz EM4100 module; IDs are reported as 10-digit decimal, like
+    /// keyboard-wedge readers print them.
+    Rdm6300,
+    /// NXP PN532 NFC module in UART mode.
+    Pn532,
+}
+
+impl SerialProtocol {
+    fn default_baud(self) -> u32 {
+        match self {
+            SerialProtocol::Rdm6300 => 9600,
+            SerialProtocol::Pn532 => 115200,
+        }
+    }
+}
+
+impl fmt::Display for SerialProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerialProtocol::Rdm6300 => write!(f, "rdm6300"),
+            SerialProtocol::Pn532 => write!(f, "pn532"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Backend {
+    #[default]
+    Evdev,
+    Pcsc,
+    Serial,
+    Mock,
+}
+
+/// Every backend's fields side by side, as written in the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawReaderConfig {
+    backend: Option<Backend>,
+    name: Option<String>,
+    vendor: Option<u16>,
+    product: Option<u16>,
+    phys: Option<String>,
+    charset: Option<Charset>,
+    terminator: Option<Terminator>,
+    case: Option<Case>,
+    format: Option<Format>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    key_timeout: Option<f64>,
+    reader: Option<String>,
+    port: Option<PathBuf>,
+    protocol: Option<SerialProtocol>,
+    baud: Option<u32>,
+    id_from: Option<IdFrom>,
+    path: Option<PathBuf>,
+}
+
+impl RawReaderConfig {
+    /// Names of the fields that were given.
+    fn fields(&self) -> Vec<&'static str> {
+        [
+            ("name", self.name.is_some()),
+            ("vendor", self.vendor.is_some()),
+            ("product", self.product.is_some()),
+            ("phys", self.phys.is_some()),
+            ("charset", self.charset.is_some()),
+            ("terminator", self.terminator.is_some()),
+            ("case", self.case.is_some()),
+            ("format", self.format.is_some()),
+            ("min_length", self.min_length.is_some()),
+            ("max_length", self.max_length.is_some()),
+            ("key_timeout", self.key_timeout.is_some()),
+            ("reader", self.reader.is_some()),
+            ("port", self.port.is_some()),
+            ("protocol", self.protocol.is_some()),
+            ("baud", self.baud.is_some()),
+            ("id_from", self.id_from.is_some()),
+            ("path", self.path.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, given)| *given)
+        .map(|(field, _)| field)
+        .collect()
+    }
+}
+
+impl TryFrom<RawReaderConfig> for ReaderConfig {
+    type Error = String;
+
+    fn try_from(raw: RawReaderConfig) -> Result<Self, Self::Error> {
+        let backend = raw.backend.unwrap_or_default();
+        let allowed: &[&str] = match backend {
+            Backend::Evdev => &[
+                "name",
+                "vendor",
+                "product",
+                "phys",
+                "charset",
+                "terminator",
+                "case",
+                "format",
+                "min_length",
+                "max_length",
+                "key_timeout",
+            ],
+            Backend::Pcsc => &["reader", "id_from"],
+            Backend::Serial => &["port", "protocol", "baud", "id_from"],
+            Backend::Mock => &["path"],
+        };
+        if let Some(field) = raw.fields().into_iter().find(|f| !allowed.contains(f)) {
+            let backend = format!("{:?}", backend).to_lowercase();
+            return Err(format!(
+                "{} doesn't apply to the {} backend",
+                field, backend
+            ));
+        }
+
+        match backend {
+            Backend::Evdev => {
+                let device = DeviceMatch::new(raw.name, raw.vendor, raw.product, raw.phys)?;
+                let defaults = WedgeSettings::default();
+                let decoder = WedgeSettings {
+                    charset: raw.charset.unwrap_or(defaults.charset),
+                    terminator: raw.terminator.unwrap_or(defaults.terminator),
+                    case: raw.case.unwrap_or(defaults.case),
+                    format: raw.format.unwrap_or(defaults.format),
+                    min_length: raw.min_length.unwrap_or(defaults.min_length),
+                    max_length: raw.max_length,
+                    key_timeout: match raw.key_timeout {
+                        Some(secs) => Duration::try_from_secs_f64(secs)
+                            .map_err(|e| format!("key_timeout: {}", e))?,
+                        None => defaults.key_timeout,
+                    },
+                };
+                if decoder.format == Format::Em4100Hex && decoder.charset == Charset::Digits {
+                    return Err("format em4100-hex needs a hex or alphanumeric charset".into());
+                }
+                if decoder
+                    .max_length
+                    .is_some_and(|max| max < decoder.min_length)
+                {
+                    return Err("max_length is less than min_length".to_string());
+                }
+                Ok(ReaderConfig::Evdev { device, decoder })
+            }
+            Backend::Pcsc => Ok(ReaderConfig::Pcsc {
+                reader: raw.reader,
+                id_from: raw.id_from.unwrap_or_default(),
+            }),
+            Backend::Serial => {
+                let port = raw.port.ok_or("serial reader needs a port")?;
+                let protocol = raw.protocol.ok_or("serial reader needs a protocol")?;
+                if protocol == SerialProtocol::Rdm6300 && raw.id_from.is_some() {
+                    return Err("rdm6300 tags have no NDEF content, id_from doesn't apply".into());
+                }
+                let baud = raw.baud.unwrap_or(protocol.default_baud());
+                if serial::baud_rate(baud).is_none() {
+                    return Err(format!("unsupported baud rate {}", baud));
+                }
+                Ok(ReaderConfig::Serial {
+                    port,
+                    protocol,
+                    baud,
+                    id_from: raw.id_from.unwrap_or_default(),
+                })
+            }
+            Backend::Mock => Ok(ReaderConfig::Mock {
+                path: raw.path.ok_or("mock reader needs a path")?,
+            }),
+        }
+    }
+}
+
+/// The card number of EM4100 data given as 10 hex digits plus 2 of XOR
+/// checksum, as 10-digit decimal — the last four data bytes, the way
+/// keyboard-wedge readers print EM4100 cards. None if malformed.
+fn em4100_decimal(hex: &str) -> Option<String> {
+    if hex.len() != 12 || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..12)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let checksum = bytes[..5].iter().fold(0, |acc, b| acc ^ b);
+    if checksum != bytes[5] {
+        return None;
+    }
+    let number = u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+    Some(format!("{:010}", number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Entry {
+        reader: ReaderConfig,
+    }
+
+    /// Parse the fields of one `rfid_readers` entry, as written in its inline table.
+    fn parse(fields: &str) -> Result<ReaderConfig, String> {
+        toml::from_str::<Entry>(&format!("reader = {{ {} }}", fields))
+            .map(|entry| entry.reader)
+            .map_err(|e| e.message().to_string())
+    }
+
+    #[test]
+    fn evdev_is_the_default_backend() {
+        let config = parse(r#"name = "HID 413d:2107""#).unwrap();
+        assert_eq!(config, ReaderConfig::default());
+    }
+
+    #[test]
+    fn evdev_decoder_settings() {
+        let config =
+            parse(r#"vendor = 0x413d, charset = "hex", format = "em4100-hex", key_timeout = 0.5"#)
+                .unwrap();
+        let ReaderConfig::Evdev { decoder, .. } = config else {
+            panic!("expected an evdev reader, got {:?}", config);
+        };
+        assert_eq!(decoder.charset, Charset::Hex);
+        assert_eq!(decoder.format, Format::Em4100Hex);
+        assert_eq!(decoder.key_timeout, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn evdev_needs_a_device() {
+        assert_eq!(
+            parse(r#"charset = "hex""#).unwrap_err(),
+            "device needs at least one of name, vendor, product or phys"
+        );
+    }
+
+    #[test]
+    fn fields_of_other_backends_are_rejected() {
+        assert_eq!(
+            parse(r#"name = "reader", port = "/dev/ttyUSB0""#).unwrap_err(),
+            "port doesn't apply to the evdev backend"
+        );
+        assert_eq!(
+            parse(r#"backend = "pcsc", charset = "hex""#).unwrap_err(),
+            "charset doesn't apply to the pcsc backend"
+        );
+    }
+
+    #[test]
+    fn em4100_hex_needs_hex_digits() {
+        assert_eq!(
+            parse(r#"name = "reader", format = "em4100-hex""#).unwrap_err(),
+            "format em4100-hex needs a hex or alphanumeric charset"
+        );
+    }
+
+    #[test]
+    fn max_length_below_min_length() {
+        assert_eq!(
+            parse(r#"name = "reader", min_length = 10, max_length = 8"#).unwrap_err(),
+            "max_length is less than min_length"
+        );
+    }
+
+    #[test]
+    fn serial_defaults_its_baud_rate_by_protocol() {
+        let config = parse(
+            r#"backend = "serial", port = "/dev/ttyS0", protocol = "pn532", id_from = "ndef""#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ReaderConfig::Serial {
+                port: PathBuf::from("/dev/ttyS0"),
+                protocol: SerialProtocol::Pn532,
+                baud: 115200,
+                id_from: IdFrom::Ndef,
+            }
+        );
+    }
+
+    #[test]
+    fn serial_validation() {
+        assert_eq!(
+            parse(r#"backend = "serial", protocol = "rdm6300""#).unwrap_err(),
+            "serial reader needs a port"
+        );
+        assert_eq!(
+            parse(r#"backend = "serial", port = "/dev/ttyS0""#).unwrap_err(),
+            "serial reader needs a protocol"
+        );
+        assert_eq!(
+            parse(
+                r#"backend = "serial", port = "/dev/ttyS0", protocol = "rdm6300", id_from = "ndef""#
+            )
+            .unwrap_err(),
+            "rdm6300 tags have no NDEF content, id_from doesn't apply"
+        );
+        assert_eq!(
+            parse(r#"backend = "serial", port = "/dev/ttyS0", protocol = "rdm6300", baud = 12345"#)
+                .unwrap_err(),
+            "unsupported baud rate 12345"
+        );
+    }
+
+    #[test]
+    fn pcsc_and_mock() {
+        assert_eq!(
+            parse(r#"backend = "pcsc", reader = "ACR122""#).unwrap(),
+            ReaderConfig::Pcsc {
+                reader: Some("ACR122".to_string()),
+                id_from: IdFrom::Uid,
+            }
+        );
+        assert_eq!(
+            parse(r#"backend = "mock""#).unwrap_err(),
+            "mock reader needs a path"
+        );
+    }
+
+    #[test]
+    fn em4100_decimal_checks_the_checksum() {
+        // 0x0000D0C9A5 = 13683109, XOR checksum 0xBC
+        assert_eq!(
+            em4100_decimal("0000D0C9A5BC").as_deref(),
+            Some("0013683109")
+        );
+        assert_eq!(em4100_decimal("0000D0C9A5BD"), None);
+        assert_eq!(em4100_decimal("0000D0C9A5"), None);
+        assert_eq!(em4100_decimal("0000D0C9A5ZZ"), None);
+    }
+}
@@ -0,0 +1,250 @@
+use std::io;
+
+/// Type 2 tags (NTAG, Ultralight) keep user data from page 4 on.
+static FIRST_DATA_PAGE: u8 = 4;
+/// Enough for the largest NTAG (NTAG216, 888 bytes of user memory).
+static MAX_DATA_LEN: usize = 1024;
+
+/// URI record prefixes, by identifier code.
+static URI_PREFIXES: &[&str] = &[
+    "",
+    "http://www.",
+    "https://www.",
+    "http://",
+    "https://",
+    "tel:",
+    "mailto:",
+];
+
+/// The first text or URI record of the NDEF message on a Type 2 tag. `read`
+/// returns the 16 bytes (4 pages) starting at the given page; fewer is an error.
+pub fn read_type2_text(
+    mut read: impl FnMut(u8) -> io::Result<Vec<u8>>,
+) -> io::Result<Option<String>> {
+    let mut data = Vec::new();
+    let mut page = FIRST_DATA_PAGE;
+    let mut fill = |data: &mut Vec<u8>, len: usize| -> io::Result<bool> {
+        while data.len() < len {
+            if data.len() >= MAX_DATA_LEN {
+                return Ok(false);
+            }
+            let pages = read(page)?;
+            // Anything short would have us reading the same pages forever
+            if pages.len() < 16 {
+                return Err(io::Error::other(format!(
+                    "read of page {} returned {} bytes",
+                    page,
+                    pages.len()
+                )));
+            }
+            data.extend(pages);
+            page = page.saturating_add(4);
+        }
+        Ok(true)
+    };
+
+    // Walk the TLV blocks until the NDEF message
+    let mut offset = 0;
+    loop {
+        if !fill(&mut data, offset + 1)? {
+            return Ok(None);
+        }
+        let tag = data[offset];
+        match tag {
+            0x00 => {
+                offset += 1;
+                continue;
+            }
+            0xFE => return Ok(None),
+            _ => {}
+        }
+        if !fill(&mut data, offset + 4)? {
+            return Ok(None);
+        }
+        let (len, header) = match data[offset + 1] {
+            0xFF => (
+                u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize,
+                4,
+            ),
+            len => (len as usize, 2),
+        };
+        let start = offset + header;
+        if tag == 0x03 {
+            if !fill(&mut data, start + len)? {
+                return Ok(None);
+            }
+            return Ok(first_text(&data[start..start + len]));
+        }
+        offset = start + len;
+    }
+}
+
+/// The first well-known text (`T`) or URI (`U`) record of an NDEF message.
+fn first_text(message: &[u8]) -> Option<String> {
+    let mut offset = 0;
+    while offset < message.len() {
+        let flags = message[offset];
+        let short = flags & 0x10 != 0;
+        let has_id = flags & 0x08 != 0;
+        let tnf = flags & 0x07;
+        let type_len = *message.get(offset + 1)? as usize;
+        let mut pos = offset + 2;
+        let payload_len = if short {
+            pos += 1;
+            *message.get(pos - 1)? as usize
+        } else {
+            pos += 4;
+            u32::from_be_bytes(message.get(pos - 4..pos)?.try_into().ok()?) as usize
+        };
+        let id_len = if has_id {
+            pos += 1;
+            *message.get(pos - 1)? as usize
+        } else {
+            0
+        };
+        let record_type = message.get(pos..pos + type_len)?;
+        pos += type_len + id_len;
+        let payload = message.get(pos..pos + payload_len)?;
+
+        if tnf == 0x01 && record_type == b"T" {
+            let status = *payload.first()?;
+            // UTF-16 text isn't worth supporting for card IDs
+            if status & 0x80 == 0 {
+                let lang_len = (status & 0x3F) as usize;
+                let text = payload.get(1 + lang_len..)?;
+                return String::from_utf8(text.to_vec()).ok();
+            }
+        } else if tnf == 0x01 && record_type == b"U" {
+            let prefix = URI_PREFIXES.get(*payload.first()? as usize).unwrap_or(&"");
+            let rest = String::from_utf8(payload[1..].to_vec()).ok()?;
+            return Some(format!("{}{}", prefix, rest));
+        }
+        if flags & 0x40 != 0 {
+            // Message end
+            break;
+        }
+        offset = pos + payload_len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads 4 pages at a time from tag memory starting at page 4, with
+    /// zeroes past the end.
+    fn tag(memory: &[u8]) -> impl FnMut(u8) -> io::Result<Vec<u8>> + '_ {
+        move |page| {
+            let start = (page - FIRST_DATA_PAGE) as usize * 4;
+            Ok((start..start + 16)
+                .map(|i| memory.get(i).copied().unwrap_or(0))
+                .collect())
+        }
+    }
+
+    /// An NDEF message TLV around `message`, then a Terminator TLV.
+    fn ndef_tlv(message: &[u8]) -> Vec<u8> {
+        let mut tlv = vec![0x03, message.len() as u8];
+        tlv.extend(message);
+        tlv.push(0xFE);
+        tlv
+    }
+
+    /// A short well-known record of type `record_type`, the only one in its message.
+    fn short_record(record_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut record = vec![0xD1, 0x01, payload.len() as u8, record_type];
+        record.extend(payload);
+        record
+    }
+
+    #[test]
+    fn reads_a_short_text_record() {
+        let memory = ndef_tlv(&short_record(b'T', b"\x02enmario-kart"));
+        assert_eq!(
+            read_type2_text(tag(&memory)).unwrap(),
+            Some("mario-kart".to_string())
+        );
+    }
+
+    #[test]
+    fn reads_a_long_text_record() {
+        let text = "a".repeat(300);
+        let mut payload = b"\x02en".to_vec();
+        payload.extend(text.as_bytes());
+        // Not a short record: 4-byte payload length
+        let mut record = vec![0xC1, 0x01];
+        record.extend((payload.len() as u32).to_be_bytes());
+        record.push(b'T');
+        record.extend(&payload);
+        // 3-byte TLV length, since the message is over 254 bytes
+        let mut memory = vec![0x03, 0xFF];
+        memory.extend((record.len() as u16).to_be_bytes());
+        memory.extend(&record);
+        memory.push(0xFE);
+        assert_eq!(read_type2_text(tag(&memory)).unwrap(), Some(text));
+    }
+
+    #[test]
+    fn expands_uri_prefixes() {
+        let memory = ndef_tlv(&short_record(b'U', b"\x04example.com/zelda"));
+        assert_eq!(
+            read_type2_text(tag(&memory)).unwrap(),
+            Some("https://example.com/zelda".to_string())
+        );
+    }
+
+    #[test]
+    fn skips_null_and_other_tlvs_before_the_message() {
+        // Null TLVs, then a Lock Control TLV
+        let mut memory = vec![0x00, 0x00, 0x01, 0x03, 0xA0, 0x10, 0x44];
+        memory.extend(ndef_tlv(&short_record(b'T', b"\x02enmetroid")));
+        assert_eq!(
+            read_type2_text(tag(&memory)).unwrap(),
+            Some("metroid".to_string())
+        );
+    }
+
+    #[test]
+    fn terminator_tlv_means_no_message() {
+        let mut memory = vec![0xFE];
+        memory.extend(ndef_tlv(&short_record(b'T', b"\x02enunreachable")));
+        assert_eq!(read_type2_text(tag(&memory)).unwrap(), None);
+    }
+
+    #[test]
+    fn blank_tag_has_no_message() {
+        assert_eq!(read_type2_text(tag(&[])).unwrap(), None);
+    }
+
+    #[test]
+    fn skips_records_that_arent_text() {
+        // A MIME record first (not the last one), then a text record
+        let mut message = vec![0x92, 0x0A, 0x02];
+        message.extend(b"text/plainhi");
+        message.extend([0x51, 0x01, 0x07, b'T']);
+        message.extend(b"\x02endonk");
+        assert_eq!(first_text(&message), Some("donk".to_string()));
+    }
+
+    #[test]
+    fn short_page_read_is_an_error() {
+        let mut reads = 0;
+        let result = read_type2_text(|_| {
+            reads += 1;
+            Ok(Vec::new())
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "read of page 4 returned 0 bytes"
+        );
+        assert_eq!(reads, 1);
+        let result = read_type2_text(|_| Ok(vec![0x03, 0x10]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn truncated_record_is_ignored() {
+        assert_eq!(first_text(&[0xD1, 0x01, 0x20, b'T', 0x02]), None);
+    }
+}
@@ -0,0 +1,338 @@
+use super::{ndef, IdFrom, Reader, ReaderEvent};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+/// pcsc-lite's client socket, unless `PCSCLITE_CSOCK_NAME` says otherwise.
+static SOCKET_PATH: &str = "/run/pcscd/pcscd.comm";
+/// How often reader states are checked for a newly presented card.
+static POLL_INTERVAL: Duration = Duration::from_millis(250);
+static RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+// pcsc-lite's wire protocol (winscard_msg.h): a header of body size and
+// command, then the command's struct in native byte order.
+const PROTOCOL_MAJOR: u32 = 4;
+const PROTOCOL_MINOR: u32 = 4;
+const SCARD_ESTABLISH_CONTEXT: u32 = 0x01;
+const SCARD_CONNECT: u32 = 0x04;
+const SCARD_DISCONNECT: u32 = 0x06;
+const SCARD_TRANSMIT: u32 = 0x09;
+const CMD_VERSION: u32 = 0x11;
+const CMD_GET_READERS_STATE: u32 = 0x12;
+const MAX_READERNAME: usize = 128;
+const MAX_READERS: usize = 16;
+/// `READER_STATE`: name, event counter, state, sharing, ATR (padded) and its
+/// length, protocol.
+const READER_STATE_SIZE: usize = 184;
+const SCARD_PRESENT: u32 = 0x0004;
+const SCARD_SCOPE_SYSTEM: u32 = 2;
+const SCARD_SHARE_SHARED: u32 = 2;
+const SCARD_PROTOCOL_ANY: u32 = 3;
+const SCARD_LEAVE_CARD: u32 = 0;
+const MAX_RESPONSE: u32 = 258;
+
+/// GET DATA for the card's UID, supported by PC/SC contactless readers.
+static GET_UID: &[u8] = &[0xFF, 0xCA, 0x00, 0x00, 0x00];
+
+/// Reads NFC tags on any reader pcscd knows about, talking to pcscd's socket
+/// directly so libpcsclite isn't needed at build time.
+pub struct PcscReader {
+    /// Only readers whose name contains this are used.
+    filter: Option<String>,
+    id_from: IdFrom,
+}
+
+impl PcscReader {
+    pub fn new(filter: Option<String>, id_from: IdFrom) -> Self {
+        Self { filter, id_from }
+    }
+
+    fn session(&self, on_event: &mut dyn FnMut(ReaderEvent)) -> io::Result<()> {
+        let mut pcscd = Pcscd::connect()?;
+        eprintln!("PC/SC reader ready");
+        // The ID of the tag on each reader that has one, if it could be read
+        let mut present: HashMap<String, Option<String>> = HashMap::new();
+        loop {
+            for (name, state) in pcscd.readers()? {
+                if self.filter.as_ref().is_some_and(|f| !name.contains(f)) {
+                    continue;
+                }
+                if state & SCARD_PRESENT == 0 {
+                    if let Some(Some(id)) = present.remove(&name) {
+                        on_event(ReaderEvent::Removed(id));
+                    }
+                    continue;
+                }
+                if present.contains_key(&name) {
+                    continue;
+                }
+                let id = match pcscd.read_tag(&name, self.id_from) {
+                    Ok(id) => {
+                        on_event(ReaderEvent::Tapped(id.clone()));
+                        Some(id)
+                    }
+                    Err(e) => {
+                        eprintln!("[pcsc] failed to read tag on {}: {}", name, e);
+                        None
+                    }
+                };
+                present.insert(name, id);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Reader for PcscReader {
+    fn run(&mut self, on_event: &mut dyn FnMut(ReaderEvent)) {
+        loop {
+            if let Err(e) = self.session(on_event) {
+                eprintln!("[pcsc] {}, reconnecting...", e);
+            }
+            thread::sleep(RECONNECT_DELAY);
+        }
+    }
+}
+
+/// A connection to pcscd with an established context.
+struct Pcscd {
+    stream: UnixStream,
+    context: u32,
+}
+
+impl Pcscd {
+    fn connect() -> io::Result<Self> {
+        let path = std::env::var("PCSCLITE_CSOCK_NAME").unwrap_or_else(|_| SOCKET_PATH.into());
+        let mut pcscd = Self {
+            stream: UnixStream::connect(&path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?,
+            context: 0,
+        };
+
+        let reply = pcscd.request(CMD_VERSION, &words(&[PROTOCOL_MAJOR, PROTOCOL_MINOR, 0]))?;
+        let (major, minor) = (word(&reply, 0), word(&reply, 4));
+        check(
+            word(&reply, 8),
+            &format!("protocol version (pcscd speaks {}.{})", major, minor),
+        )?;
+
+        let reply = pcscd.request(SCARD_ESTABLISH_CONTEXT, &words(&[SCARD_SCOPE_SYSTEM, 0, 0]))?;
+        check(word(&reply, 8), "establishing context")?;
+        pcscd.context = word(&reply, 4);
+        Ok(pcscd)
+    }
+
+    /// Name and state flags of every reader.
+    fn readers(&mut self) -> io::Result<Vec<(String, u32)>> {
+        self.send(CMD_GET_READERS_STATE, &[])?;
+        let mut states = vec![0; MAX_READERS * READER_STATE_SIZE];
+        self.stream.read_exact(&mut states)?;
+        Ok(states
+            .chunks(READER_STATE_SIZE)
+            .filter(|state| state[0] != 0)
+            .map(|state| {
+                let name = &state[..MAX_READERNAME];
+                let len = name.iter().position(|&b| b == 0).unwrap_or(MAX_READERNAME);
+                let name = String::from_utf8_lossy(&name[..len]).into_owned();
+                (name, word(state, MAX_READERNAME + 4))
+            })
+            .collect())
+    }
+
+    /// Connect to the tag on `reader` and read its ID.
+    fn read_tag(&mut self, reader: &str, id_from: IdFrom) -> io::Result<String> {
+        let mut body = words(&[self.context]);
+        let mut name = [0u8; MAX_READERNAME];
+        let len = reader.len().min(MAX_READERNAME - 1);
+        name[..len].copy_from_slice(&reader.as_bytes()[..len]);
+        body.extend(name);
+        body.extend(words(&[SCARD_SHARE_SHARED, SCARD_PROTOCOL_ANY, 0, 0, 0]));
+        let reply = self.request(SCARD_CONNECT, &body)?;
+        check(word(&reply, MAX_READERNAME + 20), "connecting to card")?;
+        let card = word(&reply, MAX_READERNAME + 12);
+        let protocol = word(&reply, MAX_READERNAME + 16);
+
+        let id = self.tag_id(card, protocol, id_from);
+        let reply = self.request(SCARD_DISCONNECT, &words(&[card, SCARD_LEAVE_CARD, 0]))?;
+        if let Err(e) = check(word(&reply, 8), "disconnecting from card") {
+            eprintln!("[pcsc] {}", e);
+        }
+        id
+    }
+
+    fn tag_id(&mut self, card: u32, protocol: u32, id_from: IdFrom) -> io::Result<String> {
+        if id_from == IdFrom::Ndef {
+            // READ BINARY of 16 bytes, i.e. 4 pages of a Type 2 tag
+            let text = ndef::read_type2_text(|page| {
+                self.transmit(card, protocol, &[0xFF, 0xB0, 0x00, page, 0x10])
+            })?;
+            if let Some(text) = text {
+                return Ok(text);
+            }
+        }
+        let uid = self.transmit(card, protocol, GET_UID)?;
+        Ok(uid.iter().map(|b| format!("{:02X}", b)).collect())
+    }
+
+    /// Send an APDU and return the response data, without the status word.
+    fn transmit(&mut self, card: u32, protocol: u32, apdu: &[u8]) -> io::Result<Vec<u8>> {
+        let body = words(&[
+            card,
+            protocol,
+            8,
+            apdu.len() as u32,
+            protocol,
+            8,
+            MAX_RESPONSE,
+            0,
+        ]);
+        self.send(SCARD_TRANSMIT, &body)?;
+        self.stream.write_all(apdu)?;
+        let mut reply = vec![0; body.len()];
+        self.stream.read_exact(&mut reply)?;
+        check(word(&reply, 28), "transmitting to card")?;
+        let mut response = vec![0; word(&reply, 24) as usize];
+        self.stream.read_exact(&mut response)?;
+
+        match response.split_last_chunk::<2>() {
+            Some((data, [0x90, 0x00])) => Ok(data.to_vec()),
+            Some((_, [sw1, sw2])) => Err(io::Error::other(format!(
+                "card returned status {:02X}{:02X}",
+                sw1, sw2
+            ))),
+            None => Err(io::Error::other("card returned no status")),
+        }
+    }
+
+    /// Send a command and read back its struct, which has the same size.
+    fn request(&mut self, command: u32, body: &[u8]) -> io::Result<Vec<u8>> {
+        self.send(command, body)?;
+        let mut reply = vec![0; body.len()];
+        self.stream.read_exact(&mut reply)?;
+        Ok(reply)
+    }
+
+    fn send(&mut self, command: u32, body: &[u8]) -> io::Result<()> {
+        let mut message = words(&[body.len() as u32, command]);
+        message.extend(body);
+        self.stream.write_all(&message)
+    }
+}
+
+fn words(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+}
+
+fn word(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Turn a PC/SC return value into an error.
+fn check(rv: u32, what: &str) -> io::Result<()> {
+    if rv == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} failed: 0x{:08X}", what, rv)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read a request from the client side: its command and body.
+    fn request(pcscd: &mut UnixStream, body_len: usize) -> (u32, Vec<u8>) {
+        let mut header = [0u8; 8];
+        pcscd.read_exact(&mut header).unwrap();
+        assert_eq!(word(&header, 0) as usize, body_len);
+        let mut body = vec![0; body_len];
+        pcscd.read_exact(&mut body).unwrap();
+        (word(&header, 4), body)
+    }
+
+    fn set_word(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+    }
+
+    /// Plays pcscd for one reader with a tag on it, checking the layout of
+    /// every struct against winscard_msg.h.
+    fn fake_pcscd(mut pcscd: UnixStream) {
+        let (command, _) = request(&mut pcscd, 0);
+        assert_eq!(command, CMD_GET_READERS_STATE);
+        let mut states = vec![0u8; MAX_READERS * READER_STATE_SIZE];
+        states[..6].copy_from_slice(b"ACR122");
+        set_word(&mut states, MAX_READERNAME + 4, SCARD_PRESENT | 0x0020);
+        pcscd.write_all(&states).unwrap();
+
+        // connect: context, reader name, share mode, protocols, card, active protocol, rv
+        let (command, mut connect) = request(&mut pcscd, 4 + MAX_READERNAME + 20);
+        assert_eq!(command, SCARD_CONNECT);
+        assert_eq!(word(&connect, 0), 7);
+        assert_eq!(&connect[4..11], b"ACR122\0");
+        assert_eq!(word(&connect, MAX_READERNAME + 4), SCARD_SHARE_SHARED);
+        assert_eq!(word(&connect, MAX_READERNAME + 8), SCARD_PROTOCOL_ANY);
+        set_word(&mut connect, MAX_READERNAME + 12, 0x55);
+        set_word(&mut connect, MAX_READERNAME + 16, 2);
+        pcscd.write_all(&connect).unwrap();
+
+        // transmit: card, send PCI, send length, receive PCI, receive length, rv; then the APDU
+        let (command, mut transmit) = request(&mut pcscd, 32);
+        assert_eq!(command, SCARD_TRANSMIT);
+        assert_eq!(word(&transmit, 0), 0x55);
+        assert_eq!(word(&transmit, 4), 2);
+        assert_eq!(word(&transmit, 12) as usize, GET_UID.len());
+        let mut apdu = vec![0; GET_UID.len()];
+        pcscd.read_exact(&mut apdu).unwrap();
+        assert_eq!(apdu, GET_UID);
+        let response = [0x04, 0xA1, 0xB2, 0xC3, 0x90, 0x00];
+        set_word(&mut transmit, 24, response.len() as u32);
+        pcscd.write_all(&transmit).unwrap();
+        pcscd.write_all(&response).unwrap();
+
+        // disconnect: card, disposition, rv
+        let (command, disconnect) = request(&mut pcscd, 12);
+        assert_eq!(command, SCARD_DISCONNECT);
+        assert_eq!(word(&disconnect, 0), 0x55);
+        assert_eq!(word(&disconnect, 4), SCARD_LEAVE_CARD);
+        pcscd.write_all(&disconnect).unwrap();
+    }
+
+    #[test]
+    fn reads_reader_states_and_a_tag_uid() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || fake_pcscd(server));
+        let mut pcscd = Pcscd {
+            stream: client,
+            context: 7,
+        };
+
+        let readers = pcscd.readers().unwrap();
+        assert_eq!(readers.len(), 1);
+        assert_eq!(readers[0].0, "ACR122");
+        assert_ne!(readers[0].1 & SCARD_PRESENT, 0);
+        assert_eq!(pcscd.read_tag("ACR122", IdFrom::Uid).unwrap(), "04A1B2C3");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn error_status_words_fail_the_read() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || {
+            let (_, mut transmit) = request(&mut server, 32);
+            let mut apdu = vec![0; GET_UID.len()];
+            server.read_exact(&mut apdu).unwrap();
+            set_word(&mut transmit, 24, 2);
+            server.write_all(&transmit).unwrap();
+            server.write_all(&[0x6A, 0x81]).unwrap();
+        });
+        let mut pcscd = Pcscd {
+            stream: client,
+            context: 7,
+        };
+        let error = pcscd.transmit(0x55, 2, GET_UID).unwrap_err();
+        assert_eq!(error.to_string(), "card returned status 6A81");
+        server.join().unwrap();
+    }
+}
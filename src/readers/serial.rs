@@ -0,0 +1,277 @@
+use super::{em4100_decimal, ndef, IdFrom, Reader, ReaderEvent};
+use nix::libc;
+use nix::sys::termios::{self, BaudRate, SetArg, SpecialCharacterIndices};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// RDM6300 modules repeat a card's frame for as long as it's present, so a
+/// card counts as removed once its frames stop for this long.
+static REMOVAL_GAP: Duration = Duration::from_secs(1);
+/// How long to wait for a reply from a module.
+static REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+static PN532_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+pub fn baud_rate(baud: u32) -> Option<BaudRate> {
+    match baud {
+        9600 => Some(BaudRate::B9600),
+        19200 => Some(BaudRate::B19200),
+        38400 => Some(BaudRate::B38400),
+        57600 => Some(BaudRate::B57600),
+        115200 => Some(BaudRate::B115200),
+        _ => None,
+    }
+}
+
+/// Open a serial port in raw mode. Reads time out after 100ms, returning 0.
+fn open_port(path: &Path, baud: u32) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(path)?;
+    let mut settings = termios::tcgetattr(&file)?;
+    termios::cfmakeraw(&mut settings);
+    // Validated when the config was loaded
+    let baud = baud_rate(baud).unwrap_or(BaudRate::B9600);
+    termios::cfsetspeed(&mut settings, baud)?;
+    settings.control_chars[SpecialCharacterIndices::VMIN as usize] = 0;
+    settings.control_chars[SpecialCharacterIndices::VTIME as usize] = 1;
+    termios::tcsetattr(&file, SetArg::TCSANOW, &settings)?;
+    Ok(file)
+}
+
+/// An RDM6300 (or compatible) 125 kHz module. Each frame is STX, ten hex
+/// digits of data, two of checksum, ETX.
+pub struct Rdm6300 {
+    port: PathBuf,
+    baud: u32,
+}
+
+impl Rdm6300 {
+    pub fn new(port: PathBuf, baud: u32) -> Self {
+        Self { port, baud }
+    }
+
+    fn session(&self, on_event: &mut dyn FnMut(ReaderEvent)) -> io::Result<()> {
+        let mut port = open_port(&self.port, self.baud)?;
+        eprintln!("RFID reader ready on {}", self.port.display());
+        let mut frame = Vec::new();
+        // The card on the reader and when its last frame arrived
+        let mut present: Option<(String, Instant)> = None;
+        let mut byte = [0u8];
+        loop {
+            let read = port.read(&mut byte)?;
+            if present
+                .as_ref()
+                .is_some_and(|(_, at)| at.elapsed() > REMOVAL_GAP)
+            {
+                let (id, _) = present.take().unwrap();
+                on_event(ReaderEvent::Removed(id));
+            }
+            if read == 0 {
+                continue;
+            }
+            match byte[0] {
+                0x02 => frame.clear(),
+                0x03 => {
+                    let hex = std::str::from_utf8(&frame).ok();
+                    let Some(id) = hex.and_then(em4100_decimal) else {
+                        eprintln!("[serial] ignoring malformed frame {:?}", frame);
+                        continue;
+                    };
+                    match present.replace((id.clone(), Instant::now())) {
+                        Some((previous, _)) if previous == id => {}
+                        Some((previous, _)) => {
+                            on_event(ReaderEvent::Removed(previous));
+                            on_event(ReaderEvent::Tapped(id));
+                        }
+                        None => on_event(ReaderEvent::Tapped(id)),
+                    }
+                }
+                b => frame.push(b),
+            }
+        }
+    }
+}
+
+impl Reader for Rdm6300 {
+    fn run(&mut self, on_event: &mut dyn FnMut(ReaderEvent)) {
+        loop {
+            if let Err(e) = self.session(on_event) {
+                eprintln!("[serial] {}: {}, re-opening...", self.port.display(), e);
+            }
+            thread::sleep(RECONNECT_DELAY);
+        }
+    }
+}
+
+/// An NXP PN532 NFC module on a UART, polled for ISO 14443A tags.
+pub struct Pn532 {
+    port: PathBuf,
+    baud: u32,
+    id_from: IdFrom,
+}
+
+impl Pn532 {
+    pub fn new(port: PathBuf, baud: u32, id_from: IdFrom) -> Self {
+        Self {
+            port,
+            baud,
+            id_from,
+        }
+    }
+
+    fn session(&self, on_event: &mut dyn FnMut(ReaderEvent)) -> io::Result<()> {
+        let mut pn532 = Pn532Port {
+            port: open_port(&self.port, self.baud)?,
+        };
+        // Wake it from low-power mode, then use it as a plain reader with
+        // passive activation retries limited so polling returns promptly
+        pn532
+            .port
+            .write_all(&[0x55, 0x55, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])?;
+        pn532.command(0x14, &[0x01, 0x14, 0x01])?;
+        pn532.command(0x32, &[0x05, 0xFF, 0x01, 0x02])?;
+        eprintln!("NFC reader ready on {}", self.port.display());
+
+        // UID of the tag in the field, and its ID if it could be read
+        let mut present: Option<(Vec<u8>, Option<String>)> = None;
+        loop {
+            // InListPassiveTarget, one 106 kbps type A target
+            let reply = pn532.command(0x4A, &[0x01, 0x00])?;
+            let uid = if reply.first().is_none_or(|&targets| targets == 0) {
+                None
+            } else {
+                let len = *reply.get(5).ok_or_else(|| short_reply(&reply))? as usize;
+                Some(reply.get(6..6 + len).ok_or_else(|| short_reply(&reply))?)
+            };
+            if present.as_ref().map(|(uid, _)| uid.as_slice()) != uid {
+                if let Some((_, Some(id))) = present.take() {
+                    on_event(ReaderEvent::Removed(id));
+                }
+                if let Some(uid) = uid {
+                    let id = match pn532.tag_id(uid, self.id_from) {
+                        Ok(id) => {
+                            on_event(ReaderEvent::Tapped(id.clone()));
+                            Some(id)
+                        }
+                        Err(e) => {
+                            eprintln!("[serial] failed to read tag: {}", e);
+                            None
+                        }
+                    };
+                    present = Some((uid.to_vec(), id));
+                }
+            }
+            thread::sleep(PN532_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Reader for Pn532 {
+    fn run(&mut self, on_event: &mut dyn FnMut(ReaderEvent)) {
+        loop {
+            if let Err(e) = self.session(on_event) {
+                eprintln!("[serial] {}: {}, re-opening...", self.port.display(), e);
+            }
+            thread::sleep(RECONNECT_DELAY);
+        }
+    }
+}
+
+struct Pn532Port {
+    port: File,
+}
+
+impl Pn532Port {
+    fn tag_id(&mut self, uid: &[u8], id_from: IdFrom) -> io::Result<String> {
+        if id_from == IdFrom::Ndef {
+            // InDataExchange with the Type 2 READ command: 4 pages at a time
+            let text = ndef::read_type2_text(|page| {
+                let reply = self.command(0x40, &[0x01, 0x30, page])?;
+                match reply.split_first() {
+                    Some((0, data)) => Ok(data.to_vec()),
+                    _ => Err(io::Error::other(format!("read of page {} failed", page))),
+                }
+            })?;
+            if let Some(text) = text {
+                return Ok(text);
+            }
+        }
+        Ok(uid.iter().map(|b| format!("{:02X}", b)).collect())
+    }
+
+    /// Send a command frame, wait for the ACK and return the reply's data.
+    fn command(&mut self, command: u8, data: &[u8]) -> io::Result<Vec<u8>> {
+        let len = data.len() as u8 + 2;
+        let mut frame = vec![0x00, 0x00, 0xFF, len, len.wrapping_neg(), 0xD4, command];
+        frame.extend(data);
+        let sum = data
+            .iter()
+            .fold(0xD4u8.wrapping_add(command), |acc, b| acc.wrapping_add(*b));
+        frame.extend([sum.wrapping_neg(), 0x00]);
+        self.port.write_all(&frame)?;
+
+        if !self.read_frame()?.is_empty() {
+            return Err(io::Error::other("expected ACK"));
+        }
+        let reply = self.read_frame()?;
+        match reply.as_slice() {
+            [0xD5, code, rest @ ..] if *code == command + 1 => Ok(rest.to_vec()),
+            _ => Err(io::Error::other(format!("unexpected reply {:02X?}", reply))),
+        }
+    }
+
+    /// Read one frame, returning its payload (empty for an ACK).
+    fn read_frame(&mut self) -> io::Result<Vec<u8>> {
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        let mut previous = self.read_byte(deadline)?;
+        loop {
+            let byte = self.read_byte(deadline)?;
+            if previous == 0x00 && byte == 0xFF {
+                break;
+            }
+            previous = byte;
+        }
+        let len = self.read_byte(deadline)?;
+        let checksum = self.read_byte(deadline)?;
+        if (len, checksum) == (0x00, 0xFF) {
+            // ACK, then its postamble
+            self.read_byte(deadline)?;
+            return Ok(Vec::new());
+        }
+        if len == 0 || len.wrapping_add(checksum) != 0 {
+            return Err(io::Error::other("bad frame length"));
+        }
+        let mut payload = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            payload.push(self.read_byte(deadline)?);
+        }
+        let checksum = self.read_byte(deadline)?;
+        if payload.iter().fold(checksum, |acc, b| acc.wrapping_add(*b)) != 0 {
+            return Err(io::Error::other("bad frame data checksum"));
+        }
+        // Postamble
+        self.read_byte(deadline)?;
+        Ok(payload)
+    }
+
+    fn read_byte(&mut self, deadline: Instant) -> io::Result<u8> {
+        let mut byte = [0u8];
+        while self.port.read(&mut byte)? == 0 {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no reply"));
+            }
+        }
+        Ok(byte[0])
+    }
+}
+
+fn short_reply(reply: &[u8]) -> io::Error {
+    io::Error::other(format!("short reply {:02X?}", reply))
+}
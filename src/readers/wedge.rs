@@ -0,0 +1,466 @@
+use super::em4100_decimal;
+use evdev::Key;
+use serde::Deserialize;
+use std::time::{Duration, SystemTime};
+
+/// Characters a keyboard-wedge reader may type as part of a card ID.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Charset {
+    /// `0`–`9`, from the main row or the keypad.
+    #[default]
+    Digits,
+    /// Digits and `A`–`F`, for readers that print UIDs in hex.
+    Hex,
+    /// Digits and `A`–`Z`.
+    Alphanumeric,
+}
+
+impl Charset {
+    fn allows(self, c: char) -> bool {
+        match self {
+            Charset::Digits => c.is_ascii_digit(),
+            Charset::Hex => c.is_ascii_hexdigit(),
+            Charset::Alphanumeric => c.is_ascii_alphanumeric(),
+        }
+    }
+}
+
+/// The key a reader types after each ID.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Terminator {
+    /// Enter on the main block or the keypad.
+    #[default]
+    Enter,
+    Tab,
+    Space,
+}
+
+impl Terminator {
+    fn matches(self, key: Key) -> bool {
+        match self {
+            Terminator::Enter => key == Key::KEY_ENTER || key == Key::KEY_KPENTER,
+            Terminator::Tab => key == Key::KEY_TAB,
+            Terminator::Space => key == Key::KEY_SPACE,
+        }
+    }
+}
+
+/// How letters in an ID are cased. Shift never changes digits, so readers
+/// that hold Shift for the number row (as for AZERTY layouts) still work.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Case {
+    /// Uppercase while Shift is held, lowercase otherwise.
+    #[default]
+    Preserve,
+    Upper,
+    Lower,
+}
+
+/// What the typed ID looks like beyond its charset, for readers whose output
+/// can be checked.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// Anything in the charset.
+    #[default]
+    Plain,
+    /// An EM4100 card number as 10 decimal digits, which must fit in 32 bits.
+    Em4100,
+    /// An EM4100 tag as 10 hex digits of data and 2 of XOR checksum, reported
+    /// as 10 decimal digits like `em4100`.
+    Em4100Hex,
+}
+
+/// How to turn a keyboard-wedge reader's keystrokes into card IDs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WedgeSettings {
+    pub charset: Charset,
+    pub terminator: Terminator,
+    pub case: Case,
+    pub format: Format,
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+    /// A pause longer than this between keys discards what was typed so far,
+    /// so a partial read can't be glued onto the next scan.
+    pub key_timeout: Duration,
+}
+
+impl Default for WedgeSettings {
+    fn default() -> Self {
+        Self {
+            charset: Charset::default(),
+            terminator: Terminator::default(),
+            case: Case::default(),
+            format: Format::default(),
+            min_length: 1,
+            max_length: None,
+            key_timeout: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Assembles card IDs from key events. Reads are rejected (with a reason)
+/// when they're empty, contain characters outside the charset, are outside
+/// the length limits or fail the format's check.
+pub struct WedgeDecoder {
+    settings: WedgeSettings,
+    id: String,
+    /// Whether a character outside the charset was typed into `id`.
+    malformed: bool,
+    shift: bool,
+    last_key: Option<SystemTime>,
+}
+
+impl WedgeDecoder {
+    pub fn new(settings: WedgeSettings) -> Self {
+        Self {
+            settings,
+            id: String::new(),
+            malformed: false,
+            shift: false,
+            last_key: None,
+        }
+    }
+
+    /// Feed one key event (`value` 1 for press, 0 for release, 2 for repeat)
+    /// with its timestamp. Returns a read when the terminator is released, or
+    /// the reason a partial read was discarded.
+    pub fn key(&mut self, key: Key, value: i32, at: SystemTime) -> Option<Result<String, String>> {
+        if value == 2 {
+            return None;
+        }
+        let discarded = self.check_timeout(at);
+        let read = self.handle(key, value);
+        match (read, discarded) {
+            (Some(Ok(id)), _) => Some(Ok(id)),
+            (_, Some(discarded)) => Some(Err(discarded)),
+            (read, None) => read,
+        }
+    }
+
+    /// Drop a partly typed ID, e.g. after the device went away.
+    pub fn reset(&mut self) {
+        self.id.clear();
+        self.malformed = false;
+        self.shift = false;
+        self.last_key = None;
+    }
+
+    fn check_timeout(&mut self, at: SystemTime) -> Option<String> {
+        let last = self.last_key.replace(at)?;
+        let pause = at.duration_since(last).unwrap_or_default();
+        if pause <= self.settings.key_timeout || self.id.is_empty() {
+            return None;
+        }
+        let id = std::mem::take(&mut self.id);
+        self.malformed = false;
+        Some(format!(
+            "discarded partial read {:?} after a {:.0?} pause",
+            id, pause
+        ))
+    }
+
+    fn handle(&mut self, key: Key, value: i32) -> Option<Result<String, String>> {
+        if key == Key::KEY_LEFTSHIFT || key == Key::KEY_RIGHTSHIFT {
+            self.shift = value != 0;
+            return None;
+        }
+        if value != 0 {
+            return None;
+        }
+        if self.settings.terminator.matches(key) {
+            let id = std::mem::take(&mut self.id);
+            let malformed = std::mem::take(&mut self.malformed);
+            return Some(self.validate(id, malformed));
+        }
+        if let Some(c) = key_char(key) {
+            let c = match self.settings.case {
+                Case::Preserve if self.shift => c.to_ascii_uppercase(),
+                Case::Preserve | Case::Lower => c,
+                Case::Upper => c.to_ascii_uppercase(),
+            };
+            self.malformed |= !self.settings.charset.allows(c);
+            self.id.push(c);
+        }
+        None
+    }
+
+    fn validate(&self, id: String, malformed: bool) -> Result<String, String> {
+        if id.is_empty() {
+            return Err("empty read".to_string());
+        }
+        if malformed {
+            return Err(format!("unexpected characters in {:?}", id));
+        }
+        let too_long = self.settings.max_length.is_some_and(|max| id.len() > max);
+        if id.len() < self.settings.min_length || too_long {
+            return Err(format!("read {:?} has length {}", id, id.len()));
+        }
+        match self.settings.format {
+            Format::Plain => Ok(id),
+            Format::Em4100 => {
+                if id.len() == 10 && id.parse::<u32>().is_ok() {
+                    Ok(id)
+                } else {
+                    Err(format!("{:?} is not a 10-digit EM4100 card number", id))
+                }
+            }
+            Format::Em4100Hex => em4100_decimal(&id)
+                .ok_or_else(|| format!("{:?} is not EM4100 data with a valid checksum", id)),
+        }
+    }
+}
+
+/// The character a key types, lowercase for letters.
+fn key_char(key: Key) -> Option<char> {
+    let c = match key {
+        Key::KEY_0 | Key::KEY_KP0 => '0',
+        Key::KEY_1 | Key::KEY_KP1 => '1',
+        Key::KEY_2 | Key::KEY_KP2 => '2',
+        Key::KEY_3 | Key::KEY_KP3 => '3',
+        Key::KEY_4 | Key::KEY_KP4 => '4',
+        Key::KEY_5 | Key::KEY_KP5 => '5',
+        Key::KEY_6 | Key::KEY_KP6 => '6',
+        Key::KEY_7 | Key::KEY_KP7 => '7',
+        Key::KEY_8 | Key::KEY_KP8 => '8',
+        Key::KEY_9 | Key::KEY_KP9 => '9',
+        Key::KEY_A => 'a',
+        Key::KEY_B => 'b',
+        Key::KEY_C => 'c',
+        Key::KEY_D => 'd',
+        Key::KEY_E => 'e',
+        Key::KEY_F => 'f',
+        Key::KEY_G => 'g',
+        Key::KEY_H => 'h',
+        Key::KEY_I => 'i',
+        Key::KEY_J => 'j',
+        Key::KEY_K => 'k',
+        Key::KEY_L => 'l',
+        Key::KEY_M => 'm',
+        Key::KEY_N => 'n',
+        Key::KEY_O => 'o',
+        Key::KEY_P => 'p',
+        Key::KEY_Q => 'q',
+        Key::KEY_R => 'r',
+        Key::KEY_S => 's',
+        Key::KEY_T => 't',
+        Key::KEY_U => 'u',
+        Key::KEY_V => 'v',
+        Key::KEY_W => 'w',
+        Key::KEY_X => 'x',
+        Key::KEY_Y => 'y',
+        Key::KEY_Z => 'z',
+        _ => return None,
+    };
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader typing into a decoder, 5ms per key event unless told to pause.
+    struct Reader {
+        decoder: WedgeDecoder,
+        at: SystemTime,
+        reads: Vec<Result<String, String>>,
+    }
+
+    impl Reader {
+        fn new(settings: WedgeSettings) -> Self {
+            Self {
+                decoder: WedgeDecoder::new(settings),
+                at: SystemTime::UNIX_EPOCH,
+                reads: Vec::new(),
+            }
+        }
+
+        fn event(&mut self, key: Key, value: i32) {
+            self.at += Duration::from_millis(5);
+            if let Some(read) = self.decoder.key(key, value, self.at) {
+                self.reads.push(read);
+            }
+        }
+
+        fn tap(&mut self, key: Key) {
+            self.event(key, 1);
+            self.event(key, 0);
+        }
+
+        /// Type `text` on the main row, holding Shift for uppercase letters.
+        fn type_text(&mut self, text: &str) {
+            for c in text.chars() {
+                let key = (0..0x100)
+                    .map(Key::new)
+                    .find(|&key| key_char(key) == Some(c.to_ascii_lowercase()))
+                    .unwrap();
+                if c.is_ascii_uppercase() {
+                    self.event(Key::KEY_LEFTSHIFT, 1);
+                    self.tap(key);
+                    self.event(Key::KEY_LEFTSHIFT, 0);
+                } else {
+                    self.tap(key);
+                }
+            }
+        }
+
+        fn scan(&mut self, text: &str) {
+            self.type_text(text);
+            self.tap(Key::KEY_ENTER);
+        }
+
+        fn pause(&mut self, pause: Duration) {
+            self.at += pause;
+        }
+    }
+
+    fn read(settings: WedgeSettings, text: &str) -> Result<String, String> {
+        let mut reader = Reader::new(settings);
+        reader.scan(text);
+        assert_eq!(reader.reads.len(), 1, "reads: {:?}", reader.reads);
+        reader.reads.remove(0)
+    }
+
+    #[test]
+    fn reads_a_scan() {
+        assert_eq!(
+            read(WedgeSettings::default(), "0005593265"),
+            Ok("0005593265".to_string())
+        );
+    }
+
+    #[test]
+    fn partial_read_is_not_glued_onto_the_next_scan() {
+        let mut reader = Reader::new(WedgeSettings::default());
+        reader.type_text("00055");
+        reader.pause(Duration::from_secs(1));
+        reader.scan("0001234567");
+        assert_eq!(
+            reader.reads,
+            [
+                Err("discarded partial read \"00055\" after a 1s pause".to_string()),
+                Ok("0001234567".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn pauses_between_scans_are_fine() {
+        let mut reader = Reader::new(WedgeSettings::default());
+        reader.scan("0005593265");
+        reader.pause(Duration::from_secs(10));
+        reader.scan("0001234567");
+        assert_eq!(
+            reader.reads,
+            [Ok("0005593265".to_string()), Ok("0001234567".to_string())]
+        );
+    }
+
+    #[test]
+    fn stray_enter_is_rejected() {
+        let mut reader = Reader::new(WedgeSettings::default());
+        reader.tap(Key::KEY_KPENTER);
+        assert_eq!(reader.reads, [Err("empty read".to_string())]);
+    }
+
+    #[test]
+    fn keypad_digits_are_decoded() {
+        let mut reader = Reader::new(WedgeSettings::default());
+        for key in [Key::KEY_KP0, Key::KEY_KP4, Key::KEY_KP2, Key::KEY_KP9] {
+            reader.tap(key);
+        }
+        reader.tap(Key::KEY_KPENTER);
+        assert_eq!(reader.reads, [Ok("0429".to_string())]);
+    }
+
+    #[test]
+    fn hex_with_shift_is_decoded() {
+        let hex = WedgeSettings {
+            charset: Charset::Hex,
+            ..WedgeSettings::default()
+        };
+        assert_eq!(read(hex.clone(), "04A1B2c3"), Ok("04A1B2c3".to_string()));
+        let upper = WedgeSettings {
+            case: Case::Upper,
+            ..hex
+        };
+        assert_eq!(read(upper, "04a1B2c3"), Ok("04A1B2C3".to_string()));
+    }
+
+    #[test]
+    fn characters_outside_the_charset_are_rejected() {
+        assert_eq!(
+            read(WedgeSettings::default(), "00A5"),
+            Err("unexpected characters in \"00A5\"".to_string())
+        );
+    }
+
+    #[test]
+    fn length_limits_are_enforced() {
+        let settings = WedgeSettings {
+            min_length: 8,
+            max_length: Some(10),
+            ..WedgeSettings::default()
+        };
+        assert_eq!(
+            read(settings.clone(), "1234567"),
+            Err("read \"1234567\" has length 7".to_string())
+        );
+        assert_eq!(
+            read(settings.clone(), "12345678901"),
+            Err("read \"12345678901\" has length 11".to_string())
+        );
+        assert_eq!(read(settings, "12345678"), Ok("12345678".to_string()));
+    }
+
+    #[test]
+    fn em4100_numbers_must_fit_in_32_bits() {
+        let settings = WedgeSettings {
+            format: Format::Em4100,
+            ..WedgeSettings::default()
+        };
+        assert_eq!(
+            read(settings.clone(), "4294967295"),
+            Ok("4294967295".to_string())
+        );
+        assert_eq!(
+            read(settings.clone(), "4294967296"),
+            Err("\"4294967296\" is not a 10-digit EM4100 card number".to_string())
+        );
+        assert_eq!(
+            read(settings, "123456789"),
+            Err("\"123456789\" is not a 10-digit EM4100 card number".to_string())
+        );
+    }
+
+    #[test]
+    fn em4100_hex_checksum_is_verified() {
+        let settings = WedgeSettings {
+            charset: Charset::Hex,
+            format: Format::Em4100Hex,
+            ..WedgeSettings::default()
+        };
+        // Data 0000D0C9A5 has XOR checksum BC; its card number is 0x00D0C9A5
+        assert_eq!(
+            read(settings.clone(), "0000D0C9A5BC"),
+            Ok("0013683109".to_string())
+        );
+        assert_eq!(
+            read(settings, "0000D0C9A5BD"),
+            Err("\"0000D0C9A5BD\" is not EM4100 data with a valid checksum".to_string())
+        );
+    }
+
+    #[test]
+    fn key_repeats_are_ignored() {
+        let mut reader = Reader::new(WedgeSettings::default());
+        reader.event(Key::KEY_7, 1);
+        reader.event(Key::KEY_7, 2);
+        reader.event(Key::KEY_7, 0);
+        reader.tap(Key::KEY_ENTER);
+        assert_eq!(reader.reads, [Ok("7".to_string())]);
+    }
+}
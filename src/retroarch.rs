@@ -0,0 +1,50 @@
+use crate::config::{CardInfo, LaunchTarget};
+use std::io;
+use std::net::UdpSocket;
+
+/// RetroArch's default `network_cmd_port`.
+static NETWORK_CMD_PORT: u16 = 55355;
+
+/// Settings layered on for every RetroArch launch: quit on the first request
+/// rather than waiting for confirmation.
+pub static LAUNCH_SETTINGS: &[(&str, &str)] = &[("quit_press_twice", "false")];
+
+/// Settings layered on for cards with `resume = true`: save state when RetroArch
+/// quits and load it on the next launch.
+pub static RESUME_SETTINGS: &[(&str, &str)] = &[
+    ("savestate_auto_save", "true"),
+    ("savestate_auto_load", "true"),
+];
+
+/// The port a card's RetroArch listens for network commands on, if the card
+/// turned them on in its settings. They're off by default, since RetroArch
+/// takes commands from anyone who can reach the port.
+pub fn network_cmd_port(card: &CardInfo) -> Option<u16> {
+    let LaunchTarget::RetroArch { settings, .. } = &card.launch else {
+        return None;
+    };
+    if settings
+        .get("network_cmd_enable")
+        .is_none_or(|v| v != "true")
+    {
+        return None;
+    }
+    Some(
+        settings
+            .get("network_cmd_port")
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(NETWORK_CMD_PORT),
+    )
+}
+
+/// Show a message on screen in a running RetroArch.
+pub fn show_message(port: u16, message: &str) -> io::Result<()> {
+    send_command(port, &format!("SHOW_MSG {}", message))
+}
+
+/// Send a command (e.g. `QUIT`) to a local RetroArch's network command interface.
+pub fn send_command(port: u16, command: &str) -> io::Result<()> {
+    let socket = UdpSocket::bind(("127.0.0.1", 0))?;
+    socket.send_to(command.as_bytes(), ("127.0.0.1", port))?;
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use crate::assets::load_texture;
-use crate::ui::draw_preview;
+use crate::ui::{draw_countdown, draw_preview};
 use eframe::egui::{self, TextureHandle};
+use std::time::{Duration, Instant};
 
 static ANIMATION_TIME: f64 = 0.6;
 static TRAVEL_DISTANCE: f64 = 800.0;
@@ -17,7 +18,13 @@ enum AnimationState {
 pub struct RomPreview {
     state: AnimationState,
     next_rom: Option<String>,
+    next_error: bool,
+    next_countdown: Option<(Instant, Instant)>,
     texture: Option<TextureHandle>,
+    /// Whether the current card is shown as an error (e.g. it failed to launch).
+    error: bool,
+    /// When the current card's countdown started and when it runs out.
+    countdown: Option<(Instant, Instant)>,
     start_time: Option<f64>,
 }
 
@@ -26,13 +33,21 @@ impl RomPreview {
         Self {
             state: AnimationState::Offscreen,
             next_rom: None,
+            next_error: false,
+            next_countdown: None,
             texture: None,
+            error: false,
+            countdown: None,
             start_time: None,
         }
     }
 
-    pub fn enqueue(&mut self, next_rom: Option<String>) {
+    /// Show `next_rom` next, with a countdown starting now if given.
+    pub fn enqueue(&mut self, next_rom: Option<String>, error: bool, countdown: Option<Duration>) {
         self.next_rom = next_rom;
+        self.next_error = error;
+        let now = Instant::now();
+        self.next_countdown = countdown.map(|countdown| (now, now + countdown));
         match self.state {
             AnimationState::Active => {
                 self.state = AnimationState::FlyingOut;
@@ -75,7 +90,16 @@ impl RomPreview {
             }
         };
 
-        draw_preview(ui, offset, opacity, &self.texture);
+        draw_preview(ui, offset, opacity, &self.texture, self.error);
+        if let Some((start, end)) = self.countdown {
+            let now = Instant::now();
+            if now < end {
+                let left = end - now;
+                let fraction = left.as_secs_f32() / (end - start).as_secs_f32();
+                draw_countdown(ui, opacity, left, fraction);
+                ctx.request_repaint_after(Duration::from_millis(50));
+            }
+        }
 
         self.resolve_animation_state(current_time, ctx);
     }
@@ -111,6 +135,8 @@ impl RomPreview {
                 match &self.next_rom {
                     Some(path) => {
                         self.texture = load_texture(ctx, path);
+                        self.error = self.next_error;
+                        self.countdown = self.next_countdown.take();
                         self.next_rom = None;
                         ctx.request_repaint();
                         AnimationState::FlyingIn
@@ -1,4 +1,5 @@
 use eframe::egui::{self, Context, TextureHandle};
+use std::time::Duration;
 
 pub fn draw_background(ctx: &Context, texture: &TextureHandle) {
     let painter = ctx.layer_painter(egui::LayerId::background());
@@ -36,7 +37,43 @@ pub fn draw_header(ui: &mut egui::Ui, text: &str) {
     ui.add_space(72.0);
 }
 
-pub fn draw_preview(ui: &mut egui::Ui, offset: f64, opacity: f64, texture: &Option<TextureHandle>) {
+/// A bar under the preview that shrinks as `fraction` goes to 0, with the
+/// seconds left.
+pub fn draw_countdown(ui: &mut egui::Ui, opacity: f64, left: Duration, fraction: f32) {
+    ui.add_space(28.0);
+    let mut painter = ui.painter().clone();
+    painter.set_opacity(opacity as f32);
+
+    let (rect, _) = ui.allocate_exact_size(egui::Vec2::new(400.0, 8.0), egui::Sense::hover());
+    painter.rect_filled(
+        rect,
+        egui::Rounding::same(4.0),
+        egui::Color32::from_rgba_premultiplied(0, 0, 0, 51),
+    );
+    let mut remaining = rect;
+    remaining.set_width(rect.width() * fraction.clamp(0.0, 1.0));
+    painter.rect_filled(
+        remaining,
+        egui::Rounding::same(4.0),
+        egui::Color32::from_rgb(238, 238, 187),
+    );
+
+    painter.text(
+        rect.center_bottom() + egui::vec2(0.0, 12.0),
+        egui::Align2::CENTER_TOP,
+        left.as_secs_f32().ceil().to_string(),
+        egui::FontId::proportional(28.0),
+        egui::Color32::from_rgb(238, 238, 187),
+    );
+}
+
+pub fn draw_preview(
+    ui: &mut egui::Ui,
+    offset: f64,
+    opacity: f64,
+    texture: &Option<TextureHandle>,
+    error: bool,
+) {
     let preview_size = egui::Vec2::new(400.0, 400.0);
     let available_size = ui.available_size();
     let center_y = (available_size.y - preview_size.y) / 2.0;
@@ -53,10 +90,15 @@ pub fn draw_preview(ui: &mut egui::Ui, offset: f64, opacity: f64, texture: &Opti
         egui::Color32::from_rgba_premultiplied(0, 0, 0, 51),
     );
 
+    let border = if error {
+        egui::Color32::from_rgb(220, 70, 60)
+    } else {
+        egui::Color32::from_rgb(238, 238, 187)
+    };
     painter.rect_stroke(
         rect.expand(8.0),
         egui::Rounding::same(20.0),
-        egui::Stroke::new(8.0, egui::Color32::from_rgb(238, 238, 187)),
+        egui::Stroke::new(8.0, border),
     );
 
     if let Some(texture) = texture.as_ref() {
@@ -88,3 +130,30 @@ pub fn draw_preview(ui: &mut egui::Ui, offset: f64, opacity: f64, texture: &Opti
         );
     }
 }
+
+/// Draw a message banner along the bottom of the screen, above everything else.
+pub fn draw_message(ctx: &Context, text: &str) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("message"),
+    ));
+    let screen_rect = ctx.screen_rect();
+
+    let galley = painter.layout(
+        text.to_string(),
+        egui::FontId::proportional(40.0),
+        egui::Color32::from_rgb(238, 238, 187),
+        screen_rect.width() - 160.0,
+    );
+    let rect = egui::Rect::from_center_size(
+        egui::Pos2::new(screen_rect.center().x, screen_rect.bottom() - 100.0),
+        galley.size(),
+    );
+
+    painter.rect_filled(
+        rect.expand(24.0),
+        egui::Rounding::same(20.0),
+        egui::Color32::from_rgba_premultiplied(0, 0, 0, 153),
+    );
+    painter.galley(rect.min, galley, egui::Color32::WHITE);
+}